@@ -0,0 +1,403 @@
+use chess::*;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::str::FromStr;
+use super::book::{self, Book, Entry, Format};
+use super::config;
+
+const DEFAULT_MIN_GAMES: u32 = 2;
+
+/// what goes into the book and how it's written out
+struct Options {
+    min_elo: u32,
+    results: Vec<&'static str>,
+    plies: usize,
+    min_games: u32,
+    format: Format,
+}
+
+/// a game from a pgn file, the moves still in san
+struct PgnGame {
+    headers: HashMap<String, String>,
+    moves: Vec<String>,
+}
+
+/// games and points of a move in one position, 2 for a win and 1 for a draw of the side playing it
+#[derive(Default)]
+struct Stats {
+    games: u32,
+    score: u32,
+}
+
+/// builds an opening book: `book build <out> <pgn>... [--min-elo N] [--results 1-0,0-1,1/2-1/2]
+/// [--plies N] [--min-games N] [--format polyglot|native]`
+pub fn run(args: &[String]) {
+    let Some((out, options, files)) = parse_args(args) else {
+        warn!("usage: book build <out> <pgn>... [--min-elo N] [--results 1-0,0-1,1/2-1/2] [--plies N] [--min-games N] [--format polyglot|native]");
+        return;
+    };
+
+    let mut stats = HashMap::<(u64, u16), Stats>::new();
+    let (mut read, mut used) = (0, 0);
+
+    for file in files {
+        let reader = match File::open(file) {
+            Ok(f) => BufReader::new(f),
+            Err(e) => {
+                warn!("failed to read `{}` ({})", file, e);
+                continue;
+            },
+        };
+
+        // games are counted as they're read, collections can be too big to hold in memory
+        for game in PgnReader::new(reader) {
+            read += 1;
+
+            if add_game(&game, &options, &mut stats) {
+                used += 1;
+            }
+        }
+    }
+
+    info!("used {} of {} games", used, read);
+
+    let book = Book::from_entries(make_entries(stats, options.min_games));
+
+    match book.save(out, options.format) {
+        Ok(()) => {
+            info!("saved {} book entries to `{}`", book.entries().len(), out);
+        },
+        Err(e) => {
+            warn!("failed to save opening book to `{}` ({})", out, e);
+        },
+    }
+}
+
+fn parse_args(args: &[String]) -> Option<(&String, Options, Vec<&String>)> {
+    let (out, rest) = args.split_first()?;
+
+    let mut options = Options {
+        min_elo: 0,
+        results: vec!["1-0", "0-1", "1/2-1/2"],
+        plies: config::DEFAULT_BOOK_DEPTH,
+        min_games: DEFAULT_MIN_GAMES,
+        format: Format::default(),
+    };
+    let mut files = Vec::new();
+    let mut args = rest.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--min-elo" => options.min_elo = args.next()?.parse().ok()?,
+            "--plies" => options.plies = args.next()?.parse().ok()?,
+            "--min-games" => options.min_games = args.next()?.parse().ok()?,
+            "--format" => options.format = args.next()?.parse().ok()?,
+            "--results" => {
+                options.results = args.next()?.split(',').map(|r| match r.trim() {
+                    "1-0" => Some("1-0"),
+                    "0-1" => Some("0-1"),
+                    "1/2-1/2" => Some("1/2-1/2"),
+                    _ => None,
+                }).collect::<Option<_>>()?;
+            },
+            flag if flag.starts_with("--") => return None,
+            _ => files.push(arg),
+        }
+    }
+
+    (!files.is_empty()).then_some((out, options, files))
+}
+
+/// replays the game and counts its moves, false if it got filtered out
+fn add_game(game: &PgnGame, options: &Options, stats: &mut HashMap<(u64, u16), Stats>) -> bool {
+    let elo = |tag| game.headers.get(tag).and_then(|e| e.parse::<u32>().ok()).unwrap_or(0);
+
+    if elo("WhiteElo").min(elo("BlackElo")) < options.min_elo {
+        return false;
+    }
+
+    // points for white, out of 2
+    let result = game.headers.get("Result").map(String::as_str).unwrap_or("*");
+    let white_score = match result {
+        "1-0" => 2,
+        "1/2-1/2" => 1,
+        "0-1" => 0,
+        _ => return false,
+    };
+
+    if !options.results.contains(&result) {
+        return false;
+    }
+
+    let mut board = match game.headers.get("FEN") {
+        Some(fen) => match Board::from_str(fen) {
+            Ok(board) => board,
+            Err(_) => return false,
+        },
+        None => Board::default(),
+    };
+
+    for san in game.moves.iter().take(options.plies) {
+        // `from_san` wants no check marks or annotations, letter o castling and `e8Q` promotions
+        let san = san.trim_end_matches(['+', '#', '!', '?']).replace('0', "O").replace('=', "");
+
+        let Ok(m) = ChessMove::from_san(&board, &san) else {
+            let tag = |name| game.headers.get(name).map(String::as_str).unwrap_or("?");
+            warn!("unreadable move `{}` in {} - {} ({}, round {}), skipping the rest", san, tag("White"), tag("Black"), tag("Event"), tag("Round"));
+            break;
+        };
+
+        let score = if board.side_to_move() == Color::White { white_score } else { 2 - white_score };
+        let entry = stats.entry((book::key(&board), book::encode_move(&board, m))).or_default();
+        entry.games += 1;
+        entry.score += score;
+
+        board = board.make_move_new(m);
+    }
+
+    true
+}
+
+/// weights are the points scored, scaled down to fit if needed. moves that never scored are left
+/// out since polyglot readers don't play weight 0 anyway
+fn make_entries(stats: HashMap<(u64, u16), Stats>, min_games: u32) -> Vec<Entry> {
+    let stats = stats.into_iter().filter(|(_, s)| s.games >= min_games && s.score > 0).collect::<Vec<_>>();
+    let max = stats.iter().map(|(_, s)| s.score).max().unwrap_or(0);
+    let scale = max.div_ceil(u16::MAX as u32).max(1);
+
+    let mut entries = stats.into_iter().map(|((key, raw_move), s)| Entry {
+        key,
+        raw_move,
+        weight: (s.score / scale).max(1) as u16,
+    }).collect::<Vec<_>>();
+
+    // best moves first within a position, the order `Book::from_entries` keeps. ties go by move so
+    // the same games always make the same file
+    entries.sort_by_key(|e| (std::cmp::Reverse(e.weight), e.raw_move));
+    entries
+}
+
+/// reads games one at a time, tag pairs and main line moves only, skipping comments, variations,
+/// move numbers and numeric annotations
+struct PgnReader<R> {
+    lines: std::io::Lines<R>,
+    headers: HashMap<String, String>,
+    movetext: String,
+}
+
+impl<R: BufRead> PgnReader<R> {
+    fn new(reader: R) -> Self {
+        Self { lines: reader.lines(), headers: HashMap::new(), movetext: String::new() }
+    }
+
+    fn finish(&mut self) -> Option<PgnGame> {
+        if self.headers.is_empty() && self.movetext.trim().is_empty() {
+            return None;
+        }
+
+        let game = PgnGame { headers: std::mem::take(&mut self.headers), moves: parse_movetext(&self.movetext) };
+        self.movetext.clear();
+        Some(game)
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = PgnGame;
+
+    fn next(&mut self) -> Option<PgnGame> {
+        loop {
+            let line = match self.lines.next() {
+                Some(Ok(line)) => line,
+                Some(Err(e)) => {
+                    warn!("failed to read pgn ({})", e);
+                    return self.finish();
+                },
+                None => return self.finish(),
+            };
+            let line = line.trim();
+
+            if let Some(tag) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                // a tag after moves starts the next game
+                let game = if self.movetext.trim().is_empty() { None } else { self.finish() };
+
+                if let Some((name, value)) = tag.split_once(' ') {
+                    self.headers.insert(name.to_string(), value.trim().trim_matches('"').to_string());
+                }
+
+                if game.is_some() {
+                    return game;
+                }
+            } else if !line.starts_with('%') {
+                self.movetext += line;
+                self.movetext += "\n";
+            }
+        }
+    }
+}
+
+fn parse_movetext(text: &str) -> Vec<String> {
+    let mut moves = Vec::new();
+    let mut token = String::new();
+    let mut variation = 0;
+    let mut chars = text.chars();
+
+    let mut push = |token: &mut String, variation: usize| {
+        // move numbers can be glued to the move like `1.e4`
+        let san = token.rsplit('.').next().unwrap_or("");
+
+        if variation == 0
+            && !san.is_empty()
+            && !san.starts_with('$')
+            && !san.chars().all(|c| c.is_ascii_digit())
+            && !matches!(san, "1-0" | "0-1" | "1/2-1/2" | "*")
+        {
+            moves.push(san.to_string());
+        }
+
+        token.clear();
+    };
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                push(&mut token, variation);
+                chars.by_ref().find(|&c| c == '}');
+            },
+            ';' => {
+                push(&mut token, variation);
+                chars.by_ref().find(|&c| c == '\n');
+            },
+            '(' => {
+                push(&mut token, variation);
+                variation += 1;
+            },
+            ')' => {
+                push(&mut token, variation);
+                variation = variation.saturating_sub(1);
+            },
+            c if c.is_whitespace() => push(&mut token, variation),
+            c => token.push(c),
+        }
+    }
+
+    push(&mut token, variation);
+
+    moves
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::book::Selection;
+
+    const PGN: &str = r#"
+[Event "a"]
+[WhiteElo "2400"]
+[BlackElo "2300"]
+[Result "1-0"]
+
+1. e4 {best by test} e5 (1... c5 2. Nf3) 2. Nf3 $1 Nc6 3. Bc4 Nf6 4. O-O Bc5 5. d3 d6 1-0
+
+[Event "b"]
+[WhiteElo "2500"]
+[BlackElo "2450"]
+[Result "1/2-1/2"]
+
+1.e4 e5 2.Nf3 Nf6 ; petrov
+3.Nxe5 d6 1/2-1/2
+
+[Event "c"]
+[WhiteElo "1500"]
+[BlackElo "1600"]
+[Result "0-1"]
+
+1. d4 d5 0-1
+
+[Event "d"]
+[WhiteElo "2600"]
+[BlackElo "2600"]
+[Result "*"]
+
+1. c4 *
+
+[Event "e"]
+[SetUp "1"]
+[FEN "8/4P1k1/8/8/8/8/8/4K3 w - - 0 1"]
+[Result "1-0"]
+
+1. e8=Q+ Kf6 1-0
+"#;
+
+    fn options(min_elo: u32, min_games: u32) -> Options {
+        Options {
+            min_elo,
+            results: vec!["1-0", "0-1", "1/2-1/2"],
+            plies: 8,
+            min_games,
+            format: Format::Native,
+        }
+    }
+
+    fn build(options: &Options) -> Book {
+        let mut stats = HashMap::new();
+
+        for game in PgnReader::new(PGN.as_bytes()) {
+            add_game(&game, options, &mut stats);
+        }
+
+        Book::from_entries(make_entries(stats, options.min_games))
+    }
+
+    fn moves(book: &Book, board: &Board) -> Vec<(String, u16)> {
+        book.moves(board).into_iter().map(|(m, w)| (m.to_string(), w)).collect()
+    }
+
+    #[test]
+    fn reads_the_main_line() {
+        let games = PgnReader::new(PGN.as_bytes()).collect::<Vec<_>>();
+
+        assert_eq!(games.len(), 5);
+        assert_eq!(games[0].moves, ["e4", "e5", "Nf3", "Nc6", "Bc4", "Nf6", "O-O", "Bc5", "d3", "d6"]);
+        assert_eq!(games[1].moves, ["e4", "e5", "Nf3", "Nf6", "Nxe5", "d6"]);
+        assert_eq!(games[1].headers["WhiteElo"], "2500");
+        assert_eq!(games[4].moves, ["e8=Q+", "Kf6"]);
+    }
+
+    #[test]
+    fn counts_and_filters_games() {
+        let book = build(&options(0, 1));
+        let start = Board::default();
+
+        // a win and a draw for e4, d4 lost and c4's game never finished
+        assert_eq!(moves(&book, &start), [("e2e4".to_string(), 3)]);
+
+        let after_e4 = start.make_move_new(ChessMove::from_str("e2e4").unwrap());
+        assert_eq!(moves(&book, &after_e4), [("e7e5".to_string(), 1)]);
+
+        // castling and promotions come back out as regular moves
+        let mut board = start;
+        for m in ["e2e4", "e7e5", "g1f3", "b8c6", "f1c4", "g8f6"] {
+            board = board.make_move_new(ChessMove::from_str(m).unwrap());
+        }
+        assert_eq!(book.pick(&board, Selection::Best).unwrap().to_string(), "e1g1");
+
+        let promotion = Board::from_str("8/4P1k1/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(book.pick(&promotion, Selection::Best).unwrap().to_string(), "e7e8q");
+
+        // only the 2500 game is left, and the sideline needs two games
+        let strong = build(&options(2400, 1));
+        assert_eq!(moves(&strong, &start), [("e2e4".to_string(), 1)]);
+        let after_e5 = after_e4.make_move_new(ChessMove::from_str("e7e5").unwrap());
+        assert_eq!(moves(&build(&options(0, 2)), &after_e5), [("g1f3".to_string(), 3)]);
+        assert!(moves(&build(&options(0, 2)), &promotion).is_empty());
+    }
+
+    #[test]
+    fn ties_are_ordered_by_move() {
+        let stats = (0..20_u16).map(|m| ((1, m), Stats { games: 2, score: 2 })).collect::<HashMap<_, _>>();
+        let entries = make_entries(stats, 1);
+
+        assert_eq!(entries.iter().map(|e| e.raw_move).collect::<Vec<_>>(), (0..20).collect::<Vec<_>>());
+    }
+}
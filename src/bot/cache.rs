@@ -0,0 +1,105 @@
+use core::mem::size_of;
+#[cfg(feature = "stats")]
+use std::sync::atomic::*;
+#[cfg(feature = "uci")]
+use rayon::prelude::*;
+use super::trans_table::HashTableEntry;
+
+/// direct mapped table of 64 bit values, keyed the same lockless way as the transposition table
+///
+/// an empty slot reads as a 0 value for key 0
+pub struct Cache {
+    inner: Box<[HashTableEntry]>,
+
+    #[cfg(feature = "stats")]
+    probes: AtomicUsize,
+    #[cfg(feature = "stats")]
+    hits: AtomicUsize,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CacheStats {
+    pub probes: usize,
+    pub hits: usize,
+}
+
+impl core::fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(
+            f,
+            "probes {} hits {} ({:.1}%)",
+            self.probes,
+            self.hits,
+            self.hits as f32 * 100.0 / self.probes.max(1) as f32,
+        )
+    }
+}
+
+impl Cache {
+    pub fn with_capacity_mb(mb: usize) -> Self {
+        let len = (mb * 1024 * 1024 / size_of::<HashTableEntry>()).max(1);
+
+        Self {
+            inner: (0..len).map(|_| HashTableEntry::default()).collect(),
+
+            #[cfg(feature = "stats")]
+            probes: AtomicUsize::new(0),
+            #[cfg(feature = "stats")]
+            hits: AtomicUsize::new(0),
+        }
+    }
+
+    #[inline(always)]
+    fn entry(&self, k: u64) -> &HashTableEntry {
+        &self.inner[((k as u128 * self.inner.len() as u128) >> 64) as usize]
+    }
+
+    #[inline(always)]
+    pub fn get(&self, k: u64) -> Option<u64> {
+        #[cfg(feature = "stats")]
+        self.probes.fetch_add(1, Ordering::Relaxed);
+
+        let (key, data) = self.entry(k).load();
+
+        if key != k {
+            return None;
+        }
+
+        #[cfg(feature = "stats")]
+        self.hits.fetch_add(1, Ordering::Relaxed);
+
+        Some(data)
+    }
+
+    #[inline(always)]
+    pub fn insert(&self, k: u64, v: u64) {
+        self.entry(k).store(k, v);
+    }
+
+    #[cfg(feature = "uci")]
+    pub fn clear(&self) {
+        self.inner.par_iter().for_each(|e| e.store(0, 0));
+    }
+
+    /// only counted with the `stats` feature
+    #[cfg_attr(not(feature = "stats"), allow(clippy::missing_const_for_fn))]
+    pub fn stats(&self) -> Option<CacheStats> {
+        #[cfg(feature = "stats")]
+        return Some(CacheStats {
+            probes: self.probes.load(Ordering::Relaxed),
+            hits: self.hits.load(Ordering::Relaxed),
+        });
+
+        #[cfg(not(feature = "stats"))]
+        None
+    }
+
+    #[cfg_attr(not(feature = "stats"), allow(clippy::missing_const_for_fn))]
+    pub fn reset_stats(&self) {
+        #[cfg(feature = "stats")]
+        {
+            self.probes.store(0, Ordering::Relaxed);
+            self.hits.store(0, Ordering::Relaxed);
+        }
+    }
+}
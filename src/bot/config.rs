@@ -2,4 +2,15 @@ pub const MAX_SEARCH_DEPTH: usize = 16;
 pub const SEARCH_EXTENSION_LIMIT: usize = 8;
 pub const REDUCED_SEARCH_DEPTH: usize = 3;
 
-pub const T_TABLE_SIZE: usize = 2_usize.pow(24); // in number of elements
+#[cfg(feature = "uci")]
+pub const DEFAULT_HASH_MB: usize = 64;
+pub const EVAL_CACHE_MB: usize = 8;
+pub const PAWN_TABLE_MB: usize = 2;
+pub const T_TABLE_CLUSTER_SIZE: usize = 4;
+
+pub const DEFAULT_CONTEMPT: i32 = 0;
+pub const MAX_CONTEMPT: i32 = 100;
+
+pub const DEFAULT_BOOK_DEPTH: usize = 16;
+
+pub const THREAD_STACK_SIZE: usize = 16 * 1024 * 1024;
@@ -0,0 +1,261 @@
+use chess::*;
+use super::eval::DARK_SQUARES;
+use super::kpk;
+use super::params::EvalParams;
+
+// scale factors are out of this, applied to the end game half of the eval
+pub const SCALE_NORMAL: i32 = 64;
+const SCALE_OPPOSITE_BISHOPS: i32 = 16;
+const SCALE_OPPOSITE_BISHOPS_WITH_PIECES: i32 = 48;
+const SCALE_NO_PAWNS: i32 = 8;
+
+// added to mop-up evals so the search happily trades down into them
+const KNOWN_WIN: i32 = 1000;
+const MOP_UP_EDGE: i32 = 10;
+const MOP_UP_KINGS: i32 = 4;
+const KBNK_CORNER: i32 = 20;
+const KPK_PAWN_RANK: i32 = 20;
+
+/// neither side can ever mate: bare kings, a single minor piece or only bishops all on one colour
+pub fn is_insufficient_material(board: &Board) -> bool {
+    let heavy = board.pieces(Piece::Pawn) | board.pieces(Piece::Rook) | board.pieces(Piece::Queen);
+
+    if heavy != EMPTY {
+        return false;
+    }
+
+    let pieces = board.combined() & !board.pieces(Piece::King);
+    let bishops = *board.pieces(Piece::Bishop);
+
+    pieces.popcnt() <= 1 || (pieces == bishops && (bishops & DARK_SQUARES == EMPTY || bishops & !DARK_SQUARES == EMPTY))
+}
+
+/// positions the search scores as draws with contempt rather than a flat eval: insufficient
+/// material and kpk endings the bitbase says are drawn
+pub fn is_dead_draw(board: &Board) -> bool {
+    if is_insufficient_material(board) {
+        return true;
+    }
+
+    let pawns = board.pieces(Piece::Pawn);
+    if board.combined().popcnt() != 3 || pawns.popcnt() != 1 {
+        return false;
+    }
+
+    let pawn = pawns.to_square();
+    let strong = board.color_on(pawn).unwrap();
+
+    !kpk::probe(strong, board.king_square(strong), pawn, board.king_square(!strong), board.side_to_move())
+}
+
+/// exact evals for endings the general eval gets wrong, picked by the material on the board and
+/// from the side to move's perspective
+pub fn probe(board: &Board, params: &EvalParams) -> Option<i32> {
+    if is_insufficient_material(board) {
+        return Some(0);
+    }
+
+    for strong in ALL_COLORS {
+        // only against a bare king
+        if board.color_combined(!strong).popcnt() != 1 {
+            continue;
+        }
+
+        let count = |piece| (board.color_combined(strong) & board.pieces(piece)).popcnt();
+
+        let eval = match [Piece::Pawn, Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen].map(count) {
+            [1, 0, 0, 0, 0] => kpk(board, strong, params),
+            [0, 1, 1, 0, 0] => kbnk(board, strong, params),
+            [0, _, _, rooks, queens] if rooks + queens > 0 => mop_up(board, strong, params),
+            _ => continue,
+        };
+
+        return Some(if board.side_to_move() == strong { eval } else { -eval });
+    }
+
+    None
+}
+
+/// how much of the end game eval the side ahead can actually convert, out of `SCALE_NORMAL`
+pub fn scale_factor(board: &Board, strong: Color, params: &EvalParams) -> i32 {
+    let weak = !strong;
+    let pawns = board.pieces(Piece::Pawn);
+    let bishops = board.pieces(Piece::Bishop);
+    let strong_pawns = board.color_combined(strong) & pawns;
+    let pieces = |color| board.color_combined(color) & !pawns & !board.pieces(Piece::King);
+
+    // opposite coloured bishops, drawish even a pawn or two up
+    let (strong_bishops, weak_bishops) = (pieces(strong) & bishops, pieces(weak) & bishops);
+    if strong_bishops.popcnt() == 1
+        && weak_bishops.popcnt() == 1
+        && (strong_bishops & DARK_SQUARES == EMPTY) != (weak_bishops & DARK_SQUARES == EMPTY)
+    {
+        return if pieces(strong) == strong_bishops && pieces(weak) == weak_bishops {
+            SCALE_OPPOSITE_BISHOPS
+        } else {
+            SCALE_OPPOSITE_BISHOPS_WITH_PIECES
+        };
+    }
+
+    // rook pawns the defending king already blocks, with nothing or the wrong bishop to help
+    for file in [File::A, File::H] {
+        if strong_pawns == EMPTY || strong_pawns & !get_file(file) != EMPTY {
+            continue;
+        }
+
+        let promotion = Square::make_square(if strong == Color::White { Rank::Eighth } else { Rank::First }, file);
+        let blocked = distance(board.king_square(weak), promotion) <= 1;
+        let wrong_bishop = pieces(strong) == strong_bishops
+            && strong_bishops.popcnt() == 1
+            && (strong_bishops & DARK_SQUARES == EMPTY) != (BitBoard::from_square(promotion) & DARK_SQUARES == EMPTY);
+
+        if blocked && (pieces(strong) == EMPTY || wrong_bishop) {
+            return 0;
+        }
+    }
+
+    // no pawns and not even a rook up, like KRKB or KNNK
+    if strong_pawns == EMPTY && material(board, strong, params) - material(board, weak, params) <= params.piece_value[2] {
+        return SCALE_NO_PAWNS;
+    }
+
+    SCALE_NORMAL
+}
+
+/// drives the bare king to the edge and brings the kings together
+fn mop_up(board: &Board, strong: Color, params: &EvalParams) -> i32 {
+    let (ours, theirs) = (board.king_square(strong), board.king_square(!strong));
+
+    material(board, strong, params) + KNOWN_WIN
+        + MOP_UP_EDGE * center_distance(theirs)
+        + MOP_UP_KINGS * (14 - manhattan_distance(ours, theirs))
+}
+
+/// exact from the bitbase, wins push the pawn and draws are flat
+fn kpk(board: &Board, strong: Color, params: &EvalParams) -> i32 {
+    let pawn = (board.color_combined(strong) & board.pieces(Piece::Pawn)).to_square();
+    let (ours, theirs) = (board.king_square(strong), board.king_square(!strong));
+
+    if !kpk::probe(strong, ours, pawn, theirs, board.side_to_move()) {
+        return 0;
+    }
+
+    let rank = pawn.get_rank().to_index() as i32;
+    let relative_rank = if strong == Color::White { rank } else { 7 - rank };
+
+    params.piece_value[0] + KNOWN_WIN + KPK_PAWN_RANK * relative_rank
+}
+
+/// only the corners the bishop covers can be mated in
+fn kbnk(board: &Board, strong: Color, params: &EvalParams) -> i32 {
+    let (ours, theirs) = (board.king_square(strong), board.king_square(!strong));
+    let dark = board.color_combined(strong) & board.pieces(Piece::Bishop) & DARK_SQUARES != EMPTY;
+
+    let corners = if dark { [Square::A1, Square::H8] } else { [Square::A8, Square::H1] };
+    let corner = corners.map(|c| distance(theirs, c)).into_iter().min().unwrap();
+
+    material(board, strong, params) + KNOWN_WIN
+        + KBNK_CORNER * (7 - corner)
+        + MOP_UP_KINGS * (14 - manhattan_distance(ours, theirs))
+}
+
+fn material(board: &Board, color: Color, params: &EvalParams) -> i32 {
+    [Piece::Pawn, Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen]
+        .into_iter()
+        .map(|piece| (board.color_combined(color) & board.pieces(piece)).popcnt() as i32 * params.piece_value[piece.to_index()])
+        .sum()
+}
+
+#[inline(always)]
+fn distance(a: Square, b: Square) -> i32 {
+    let files = a.get_file().to_index().abs_diff(b.get_file().to_index());
+    let ranks = a.get_rank().to_index().abs_diff(b.get_rank().to_index());
+    files.max(ranks) as i32
+}
+
+#[inline(always)]
+fn manhattan_distance(a: Square, b: Square) -> i32 {
+    let files = a.get_file().to_index().abs_diff(b.get_file().to_index());
+    let ranks = a.get_rank().to_index().abs_diff(b.get_rank().to_index());
+    (files + ranks) as i32
+}
+
+/// 0 on the four center squares up to 6 in the corners
+#[inline(always)]
+fn center_distance(square: Square) -> i32 {
+    let file = square.get_file().to_index() as i32;
+    let rank = square.get_rank().to_index() as i32;
+    (3 - file).max(file - 4) + (3 - rank).max(rank - 4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn board(fen: &str) -> Board {
+        Board::from_str(fen).unwrap()
+    }
+
+    #[test]
+    fn insufficient_material() {
+        for fen in [
+            "8/8/4k3/8/8/3K4/8/8 w - - 0 1",
+            "8/8/4k3/8/8/3K4/5N2/8 w - - 0 1",
+            "8/8/4k3/8/8/3K4/5B2/8 b - - 0 1",
+            "8/2b5/4k3/8/8/3K4/5B2/8 w - - 0 1",
+        ] {
+            assert!(is_insufficient_material(&board(fen)), "{fen}");
+            assert_eq!(probe(&board(fen), &EvalParams::default()), Some(0), "{fen}");
+        }
+
+        for fen in [
+            "8/3b4/4k3/8/8/3K4/5B2/8 w - - 0 1",
+            "8/8/4k3/8/8/3K4/4NN2/8 w - - 0 1",
+            "8/8/4k3/8/8/3K4/4P3/8 w - - 0 1",
+        ] {
+            assert!(!is_insufficient_material(&board(fen)), "{fen}");
+        }
+    }
+
+    #[test]
+    fn mop_up_drives_the_king_into_the_right_corner() {
+        let params = EvalParams::default();
+
+        // dark squared bishop, so a1 and h8
+        let right = probe(&board("7k/8/5K2/8/8/8/8/2B1N3 w - - 0 1"), &params).unwrap();
+        let wrong = probe(&board("k7/8/2K5/8/8/8/8/2B1N3 w - - 0 1"), &params).unwrap();
+        assert!(right > wrong);
+
+        let edge = probe(&board("4k3/8/4K3/8/8/8/8/R7 b - - 0 1"), &params).unwrap();
+        let center = probe(&board("8/8/8/4k3/8/2K5/8/R7 b - - 0 1"), &params).unwrap();
+        assert!(edge < center && center < 0);
+    }
+
+    #[test]
+    fn kpk_is_exact() {
+        let params = EvalParams::default();
+
+        assert_eq!(probe(&board("8/3k4/8/3K4/3P4/8/8/8 w - - 0 1"), &params), Some(0));
+        assert!(probe(&board("8/3k4/8/3K4/3P4/8/8/8 b - - 0 1"), &params).unwrap() < -KNOWN_WIN);
+        assert!(probe(&board("8/8/8/3p4/3k4/8/3K4/8 w - - 0 1"), &params).unwrap() < -KNOWN_WIN);
+    }
+
+    #[test]
+    fn drawish_endings_are_scaled_down() {
+        let params = EvalParams::default();
+
+        let opposite_bishops = board("8/5k2/4b3/5p2/3P1P2/2B5/5K2/8 w - - 0 1");
+        assert_eq!(scale_factor(&opposite_bishops, Color::White, &params), SCALE_OPPOSITE_BISHOPS);
+
+        // h8 is dark, the bishop is light
+        let wrong_bishop = board("7k/8/8/7P/8/3B4/8/6K1 w - - 0 1");
+        assert_eq!(scale_factor(&wrong_bishop, Color::White, &params), 0);
+
+        let rook_pawn = board("k7/8/8/P7/8/8/8/6K1 w - - 0 1");
+        assert_eq!(scale_factor(&rook_pawn, Color::White, &params), 0);
+
+        let normal = board("8/8/3k4/8/3P4/8/3K4/8 w - - 0 1");
+        assert_eq!(scale_factor(&normal, Color::White, &params), SCALE_NORMAL);
+    }
+}
@@ -1,200 +1,910 @@
 use chess::*;
+use std::ops::*;
+use super::cache::Cache;
+use super::endgame::{self, SCALE_NORMAL};
+use super::nnue::{self, Network};
+use super::params::EvalParams;
 
-pub fn evaluate(board: &Board) -> i32 {
-    let white_eval = eval_single(board, Color::White);
-    let black_eval = eval_single(board, Color::Black);
+/// `acc` has to belong to `board`, it's checked against a full recompute in debug builds
+pub fn evaluate(board: &Board, acc: &Accumulator, pawn_table: &Cache) -> i32 {
+    debug_assert_eq!(
+        *acc,
+        Accumulator::new(board, acc.params, acc.nnue.as_ref().map(|(network, _)| *network)),
+        "accumulator out of sync with {board}",
+    );
+
+    if let Some(eval) = endgame::probe(board, acc.params) {
+        return eval;
+    }
+
+    if let Some((network, values)) = &acc.nnue {
+        return network.evaluate(values, board.side_to_move());
+    }
+
+    let pawns = PawnEntry::probe(board, acc.params, pawn_table);
+
+    let attacks = [attacks(board, Color::White), attacks(board, Color::Black)];
+
+    let score = acc.score
+        + eval_single(board, Color::White, &pawns, &attacks, acc.params)
+        - eval_single(board, Color::Black, &pawns, &attacks, acc.params);
+    let scale = endgame::scale_factor(board, stronger_side(score), acc.params);
+    let eval = score.taper(acc.phase.min(MAX_PHASE), scale);
 
     let perspective = if matches!(board.side_to_move(), Color::White) { 1 } else { -1 };
 
-    (white_eval - black_eval) * perspective
+    eval * perspective
+}
+
+/// material and piece square tables are left to the `Accumulator`
+#[inline(always)]
+fn eval_single(board: &Board, color: Color, pawns: &PawnEntry, attacks: &[[BitBoard; 6]; 2], params: &EvalParams) -> Tapered {
+    eval_terms(board, color, pawns, attacks, params).into_iter().fold(Tapered::default(), |sum, (_, score)| sum + score)
+}
+
+/// shared with `trace` so the breakdown always adds up to what the search sees
+#[inline(always)]
+fn eval_terms(board: &Board, color: Color, pawns: &PawnEntry, attacks: &[[BitBoard; 6]; 2], params: &EvalParams) -> [(&'static str, Tapered); 6] {
+    let bishop_pair = if (board.color_combined(color) & board.pieces(Piece::Bishop)).popcnt() == 2 {
+        params.bishop_pair
+    } else {
+        Tapered::default()
+    };
+
+    [
+        ("pawn structure", pawns.score[color.to_index()]),
+        ("free passers", free_passed_pawns(board, color, params)),
+        ("king safety", king_safety(board, color, params)),
+        ("piece activity", piece_activity(board, color, params)),
+        ("threats", threats(board, color, attacks, params)),
+        ("bishop pair", bishop_pair),
+    ]
 }
 
+/// whoever is ahead in the end game, who the scale factors are about
 #[inline(always)]
-fn eval_single(board: &Board, color: Color) -> i32 {
-    let mut eval = 0;
+const fn stronger_side(score: Tapered) -> Color {
+    if score.end() >= 0 { Color::White } else { Color::Black }
+}
 
-    let oppo_end_weight = end_game_weight(board, !color);
+/// every term of the classical eval for both sides
+#[cfg(feature = "uci")]
+pub struct EvalTrace {
+    // white then black
+    pub terms: Vec<(&'static str, [Tapered; 2])>,
+    pub phase: i32,
+    pub scale: i32,
+    // what `evaluate` returns, which can come from the network or an endgame eval instead
+    pub eval: i32,
+    pub source: &'static str,
+    pub side_to_move: Color,
+}
 
-    eval += piece_value(board, color);
-    eval += piece_square_table(board, color, oppo_end_weight);
+#[cfg(feature = "uci")]
+pub fn trace(board: &Board, acc: &Accumulator, pawn_table: &Cache) -> EvalTrace {
+    let params = acc.params;
+    let pawns = PawnEntry::new(board, params);
 
-    // bishop pair bonus
-    if (board.color_combined(color) & board.pieces(Piece::Bishop)).popcnt() == 2 {
-        eval += PIECE_VALUE[0] / 2;
+    let mut material = [Tapered::default(); 2];
+    let mut squares = [Tapered::default(); 2];
+
+    for square in *board.combined() {
+        let (piece, color) = (board.piece_on(square).unwrap(), board.color_on(square).unwrap());
+        let idx = relative_square(square, color).to_index() | (piece.to_index() << 6);
+        let value = params.piece_value.get(piece.to_index()).copied().unwrap_or(0);
+
+        material[color.to_index()] += Tapered::new(value, value);
+        squares[color.to_index()] += params.piece_square_table[idx];
     }
 
-    eval
+    let mut terms = vec![("material", material), ("piece squares", squares)];
+    let attacks = [attacks(board, Color::White), attacks(board, Color::Black)];
+    let white = eval_terms(board, Color::White, &pawns, &attacks, params);
+    let black = eval_terms(board, Color::Black, &pawns, &attacks, params);
+    terms.extend(white.into_iter().zip(black).map(|((name, w), (_, b))| (name, [w, b])));
+
+    let total = terms.iter().fold(Tapered::default(), |sum, (_, [w, b])| sum + *w - *b);
+
+    EvalTrace {
+        terms,
+        phase: acc.phase.min(MAX_PHASE),
+        scale: endgame::scale_factor(board, stronger_side(total), params),
+        eval: evaluate(board, acc, pawn_table),
+        source: if endgame::probe(board, params).is_some() {
+            "endgame"
+        } else if acc.nnue.is_some() {
+            "network"
+        } else {
+            "classical"
+        },
+        side_to_move: board.side_to_move(),
+    }
 }
 
-pub fn piece_value(board: &Board, color: Color) -> i32 {
-    let color = board.color_combined(color);
-    (color & board.pieces(Piece::Pawn)).popcnt() as i32 * PIECE_VALUE[0]
-        + (color & board.pieces(Piece::Knight)).popcnt() as i32 * PIECE_VALUE[1]
-        + (color & board.pieces(Piece::Bishop)).popcnt() as i32 * PIECE_VALUE[2]
-        + (color & board.pieces(Piece::Rook)).popcnt() as i32 * PIECE_VALUE[3]
-        + (color & board.pieces(Piece::Queen)).popcnt() as i32 * PIECE_VALUE[4]
+#[cfg(feature = "uci")]
+impl EvalTrace {
+    /// classical eval from white's perspective before tapering
+    pub fn total(&self) -> Tapered {
+        self.terms.iter().fold(Tapered::default(), |sum, (_, [w, b])| sum + *w - *b)
+    }
 }
 
-pub fn end_game_weight(board: &Board, color: Color) -> f32 {
-    let color = board.color_combined(color);
-    let value = (color & board.pieces(Piece::Knight)).popcnt() as i32 * PIECE_VALUE[1]
-        + (color & board.pieces(Piece::Bishop)).popcnt() as i32 * PIECE_VALUE[2]
-        + (color & board.pieces(Piece::Rook)).popcnt() as i32 * PIECE_VALUE[3]
-        + (color & board.pieces(Piece::Queen)).popcnt() as i32 * PIECE_VALUE[4];
+#[cfg(feature = "uci")]
+impl std::fmt::Display for EvalTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "      term      |     white     |     black     |     total")?;
+        writeln!(f, "                |   mg     eg   |   mg     eg   |   mg     eg")?;
+        writeln!(f, "----------------+---------------+---------------+---------------")?;
 
-    // value & formula from coding adventures
-    1.0 - (value as f32 / 1650.0).min(1.0)
+        let row = |f: &mut std::fmt::Formatter<'_>, name: &str, [w, b]: [Tapered; 2]| {
+            let total = w - b;
+            writeln!(
+                f,
+                " {:<14} | {:>5}  {:>5}  | {:>5}  {:>5}  | {:>5}  {:>5}",
+                name, w.mid(), w.end(), b.mid(), b.end(), total.mid(), total.end(),
+            )
+        };
+
+        for (name, scores) in &self.terms {
+            row(f, name, *scores)?;
+        }
+
+        writeln!(f, "----------------+---------------+---------------+---------------")?;
+
+        let total = self.total();
+        writeln!(f, " {:<14} |               |               | {:>5}  {:>5}", "total", total.mid(), total.end())?;
+        writeln!(f)?;
+        writeln!(f, "phase: {} / {}", self.phase, MAX_PHASE)?;
+        writeln!(f, "scale: {} / {}", self.scale, SCALE_NORMAL)?;
+        writeln!(f, "classical eval: {} (white's perspective)", total.taper(self.phase, self.scale))?;
+        write!(
+            f,
+            "final eval: {} ({} to move, {} eval)",
+            self.eval,
+            if self.side_to_move == Color::White { "white" } else { "black" },
+            self.source,
+        )
+    }
 }
 
-pub fn piece_square_table(board: &Board, color: Color, end_weight: f32) -> i32 {
-    let mut value = 0.0;
+/// material, piece square tables and game phase, updated move by move instead of looping over
+/// the whole board on every evaluation. the network's hidden layer is kept here as well when
+/// evaluating with nnue, boxed so moves don't copy it around when it isn't used
+#[derive(Debug, Clone)]
+pub struct Accumulator<'a> {
+    // from white's perspective
+    pub score: Tapered,
+    // only capped at `MAX_PHASE` when tapering so it stays exact after promotions
+    pub phase: i32,
+    pub params: &'a EvalParams,
+    pub nnue: Option<(&'a Network, Box<nnue::Accumulator>)>,
+}
+
+impl PartialEq for Accumulator<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.score == other.score
+            && self.phase == other.phase
+            && self.nnue.as_ref().map(|(_, values)| values) == other.nnue.as_ref().map(|(_, values)| values)
+    }
+}
 
-    let our_pieces = board.color_combined(color);
+impl Eq for Accumulator<'_> {}
 
-    for mut square in our_pieces.into_iter() {
-        let typ = board.piece_on(square).unwrap();
+impl<'a> Accumulator<'a> {
+    pub fn new(board: &Board, params: &'a EvalParams, network: Option<&'a Network>) -> Self {
+        let mut acc = Self {
+            score: Tapered::default(),
+            phase: 0,
+            params,
+            nnue: network.map(|network| (network, Box::new(nnue::Accumulator::new(network, board)))),
+        };
 
-        // invert square based on perspective
-        if matches!(color, Color::Black) {
-            square = unsafe {
-                Square::new(square.to_int() ^ 63)
-            };
+        for square in *board.combined() {
+            let (piece, color) = (board.piece_on(square).unwrap(), board.color_on(square).unwrap());
+            acc.score += piece_score(params, piece, color, square);
+            acc.phase += PHASE_WEIGHT[piece.to_index()];
         }
 
-        let idx = square.to_index() | (typ.to_index() << 6);
+        acc
+    }
+
+    /// `board` is the position before `m` is made
+    pub fn make_move(&self, board: &Board, m: ChessMove) -> Self {
+        let mut acc = self.clone();
+        let color = board.side_to_move();
+        let (src, dest) = (m.get_source(), m.get_dest());
+        let piece = board.piece_on(src).unwrap();
+
+        if let Some(captured) = board.piece_on(dest) {
+            acc.remove(captured, !color, dest);
+        } else if piece == Piece::Pawn && src.get_file() != dest.get_file() {
+            // en passant, the captured pawn is next to the source square
+            acc.remove(Piece::Pawn, !color, Square::make_square(src.get_rank(), dest.get_file()));
+        }
+
+        acc.remove(piece, color, src);
+        acc.add(m.get_promotion().unwrap_or(piece), color, dest);
+
+        // castling is encoded as a king move of two files
+        if piece == Piece::King && src.get_file().to_index().abs_diff(dest.get_file().to_index()) == 2 {
+            let (from, to) = if dest.get_file() == File::G { (File::H, File::F) } else { (File::A, File::D) };
+            acc.remove(Piece::Rook, color, Square::make_square(src.get_rank(), from));
+            acc.add(Piece::Rook, color, Square::make_square(src.get_rank(), to));
+        }
+
+        acc
+    }
+
+    #[inline(always)]
+    fn add(&mut self, piece: Piece, color: Color, square: Square) {
+        self.score += piece_score(self.params, piece, color, square);
+        self.phase += PHASE_WEIGHT[piece.to_index()];
+
+        if let Some((network, values)) = &mut self.nnue {
+            values.add(network, piece, color, square);
+        }
+    }
+
+    #[inline(always)]
+    fn remove(&mut self, piece: Piece, color: Color, square: Square) {
+        self.score -= piece_score(self.params, piece, color, square);
+        self.phase -= PHASE_WEIGHT[piece.to_index()];
+
+        if let Some((network, values)) = &mut self.nnue {
+            values.remove(network, piece, color, square);
+        }
+    }
+}
+
+/// material and piece square table of a single piece from white's perspective
+#[inline(always)]
+fn piece_score(params: &EvalParams, piece: Piece, color: Color, square: Square) -> Tapered {
+    let idx = relative_square(square, color).to_index() | (piece.to_index() << 6);
+    let value = params.piece_value.get(piece.to_index()).copied().unwrap_or(0);
+    let score = params.piece_square_table[idx] + Tapered::new(value, value);
+
+    if matches!(color, Color::White) { score } else { -score }
+}
+
+/// a middle game and an end game value packed into one integer, so both are summed in one go and
+/// only interpolated once at the end
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Tapered(i32);
 
-        value += (PIECE_SQUARE_TABLE_MID[idx] as f32).mul_add(
-            1.0 - end_weight,
-            PIECE_SQUARE_TABLE_END[idx] as f32 * end_weight
-        );
+impl Tapered {
+    pub const fn new(mid: i32, end: i32) -> Self {
+        Self(((end as u32) << 16).wrapping_add(mid as u32) as i32)
     }
 
-    value as i32
+    pub const fn mid(self) -> i32 {
+        self.0 as i16 as i32
+    }
+
+    pub const fn end(self) -> i32 {
+        ((self.0 as u32).wrapping_add(0x8000) >> 16) as i16 as i32
+    }
+
+    /// integer division rounds towards zero so mirrored positions stay exactly negated
+    pub const fn taper(self, phase: i32, scale: i32) -> i32 {
+        (self.mid() * phase + self.end() * scale / SCALE_NORMAL * (MAX_PHASE - phase)) / MAX_PHASE
+    }
 }
 
-// bishop value will be +25 centipawn if in pair
-pub const PIECE_VALUE: [i32; 6] = [100, 305, 333, 563, 950, 20000];
+impl Add for Tapered {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0.wrapping_add(rhs.0))
+    }
+}
+
+impl Sub for Tapered {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0.wrapping_sub(rhs.0))
+    }
+}
+
+impl Neg for Tapered {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self(self.0.wrapping_neg())
+    }
+}
+
+impl Mul<i32> for Tapered {
+    type Output = Self;
+
+    fn mul(self, rhs: i32) -> Self {
+        Self(self.0.wrapping_mul(rhs))
+    }
+}
+
+impl AddAssign for Tapered {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Tapered {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+/// everything that only depends on where the pawns are, cached in the pawn table
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PawnEntry {
+    pub score: [Tapered; 2],
+}
+
+impl PawnEntry {
+    pub fn new(board: &Board, params: &EvalParams) -> Self {
+        Self { score: [pawn_structure(board, Color::White, params), pawn_structure(board, Color::Black, params)] }
+    }
+
+    pub fn probe(board: &Board, params: &EvalParams, pawn_table: &Cache) -> Self {
+        let k = pawn_hash(board);
+
+        pawn_table.get(k).map_or_else(|| {
+            let entry = Self::new(board, params);
+            pawn_table.insert(k, entry.pack());
+            entry
+        }, Self::unpack)
+    }
+
+    const fn pack(&self) -> u64 {
+        self.score[0].0 as u32 as u64 | (self.score[1].0 as u32 as u64) << 32
+    }
+
+    const fn unpack(data: u64) -> Self {
+        Self { score: [Tapered(data as i32), Tapered((data >> 32) as i32)] }
+    }
+}
+
+/// `Board::get_pawn_hash` is unimplemented in the `chess` crate so the pawn bitboards are mixed
+/// together instead
+pub fn pawn_hash(board: &Board) -> u64 {
+    // splitmix64 finalizer
+    const fn mix(mut x: u64) -> u64 {
+        x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        x ^ (x >> 31)
+    }
+
+    let pawns = board.pieces(Piece::Pawn);
+    mix((board.color_combined(Color::White) & pawns).0) ^ mix(!(board.color_combined(Color::Black) & pawns).0)
+}
+
+/// pawn structure terms, the square table is left to the `Accumulator`
+pub fn pawn_structure(board: &Board, color: Color, params: &EvalParams) -> Tapered {
+    let our_pawns = board.color_combined(color) & board.pieces(Piece::Pawn);
+    let their_pawns = board.color_combined(!color) & board.pieces(Piece::Pawn);
+
+    let mut score = Tapered::default();
+
+    for square in our_pawns {
+        let rank = relative_rank(square, color);
+        let file = get_file(square.get_file());
+        let adjacent = get_adjacent_files(square.get_file());
+        let ahead = forward_ranks(square, color);
+        let behind = !ahead & !get_rank(square.get_rank());
+
+        let phalanx = our_pawns & adjacent & get_rank(square.get_rank()) != EMPTY;
+        let supported = get_pawn_attacks(square, !color, our_pawns) != EMPTY;
+
+        if is_passed(square, color, their_pawns) {
+            score += params.passed_pawn[rank];
+        } else if their_pawns & file & ahead == EMPTY {
+            // candidate passer: half open file with at least as many helpers as blockers
+            let helpers = (our_pawns & adjacent & !ahead).popcnt();
+            let sentries = (their_pawns & adjacent & ahead).popcnt();
+
+            if helpers >= sentries {
+                score += params.candidate_pawn[rank];
+            }
+        }
+
+        if our_pawns & file & ahead != EMPTY {
+            score += params.doubled_pawn;
+        }
+
+        if our_pawns & adjacent == EMPTY {
+            score += params.isolated_pawn;
+        } else if our_pawns & adjacent & behind == EMPTY && !phalanx {
+            // can't be protected by a pawn and can't safely move up to get protected
+            let stop = square.forward(color).unwrap();
+
+            if get_pawn_attacks(stop, color, their_pawns) != EMPTY {
+                score += params.backward_pawn;
+            }
+        }
+
+        if phalanx {
+            score += params.phalanx_pawn[rank];
+        } else if supported {
+            score += params.supported_pawn;
+        }
+    }
+
+    score
+}
+
+/// extra bonus for passed pawns that nothing stands in front of
+pub fn free_passed_pawns(board: &Board, color: Color, params: &EvalParams) -> Tapered {
+    let our_pawns = board.color_combined(color) & board.pieces(Piece::Pawn);
+    let their_pawns = board.color_combined(!color) & board.pieces(Piece::Pawn);
+
+    let mut score = Tapered::default();
+
+    for square in our_pawns {
+        let path = forward_ranks(square, color) & get_file(square.get_file());
+
+        if path & board.combined() == EMPTY && is_passed(square, color, their_pawns) {
+            let rank = relative_rank(square, color);
+            score += params.free_passed_pawn[rank];
+        }
+    }
+
+    score
+}
+
+#[inline(always)]
+fn is_passed(square: Square, color: Color, their_pawns: BitBoard) -> bool {
+    let files = get_file(square.get_file()) | get_adjacent_files(square.get_file());
+    their_pawns & files & forward_ranks(square, color) == EMPTY
+}
+
+/// only matters while the opponent still has pieces to attack with, so it's a middle game term
+pub fn king_safety(board: &Board, color: Color, params: &EvalParams) -> Tapered {
+    let king = board.king_square(color);
+    let our_pawns = board.color_combined(color) & board.pieces(Piece::Pawn);
+    let their_pawns = board.color_combined(!color) & board.pieces(Piece::Pawn);
+    let ahead = forward_ranks(king, color);
+
+    let mut value = 0;
+
+    let king_file = king.get_file().to_index();
+
+    for file in king_file.saturating_sub(1)..=(king_file + 1).min(7) {
+        let file = get_file(File::from_index(file));
+
+        // pawn shield
+        let shield = our_pawns & file & ahead;
+        match shield.into_iter().map(|s| rank_distance(king, s)).min() {
+            Some(1) => value += params.pawn_shield[0],
+            Some(2) => value += params.pawn_shield[1],
+            _ => value += params.pawn_shield[2],
+        }
+
+        // pawn storm
+        let storm = their_pawns & file & ahead;
+        if let Some(distance) = storm.into_iter().map(|s| rank_distance(king, s)).min() {
+            value += params.pawn_storm.get(distance).unwrap_or(&0);
+        }
+
+        // open files
+        if our_pawns & file == EMPTY {
+            value += if their_pawns & file == EMPTY { params.open_file_near_king } else { params.semi_open_file_near_king };
+        }
+    }
+
+    // attack units from every piece hitting the squares around the king
+    let zone = get_king_moves(king) | BitBoard::from_square(king);
+    let blockers = *board.combined();
+    let mut units = 0;
+    let mut attackers = 0;
+
+    for square in *board.color_combined(!color) & !board.pieces(Piece::Pawn) & !board.pieces(Piece::King) {
+        let piece = board.piece_on(square).unwrap();
+        let attacks = match piece {
+            Piece::Knight => get_knight_moves(square),
+            Piece::Bishop => get_bishop_moves(square, blockers),
+            Piece::Rook => get_rook_moves(square, blockers),
+            _ => get_bishop_moves(square, blockers) | get_rook_moves(square, blockers),
+        } & zone;
+
+        if attacks != EMPTY {
+            units += params.king_attack_weight[piece.to_index()] * attacks.popcnt() as i32;
+            attackers += 1;
+        }
+    }
+
+    // a lone attacker can't do much
+    if attackers >= 2 {
+        value -= params.king_safety_table[(units.max(0) as usize).min(params.king_safety_table.len() - 1)];
+    }
+
+    Tapered::new(value, 0)
+}
+
+/// mobility, rooks on open files and the 7th rank, knight outposts and bad bishops
+pub fn piece_activity(board: &Board, color: Color, params: &EvalParams) -> Tapered {
+    let our_pawns = board.color_combined(color) & board.pieces(Piece::Pawn);
+    let their_pawns = board.color_combined(!color) & board.pieces(Piece::Pawn);
+    let blockers = *board.combined();
+
+    // squares covered by enemy pawns aren't worth much to a piece
+    let mut pawn_attacks = EMPTY;
+    for square in their_pawns {
+        pawn_attacks |= get_pawn_attacks(square, !color, !EMPTY);
+    }
+    let safe = !board.color_combined(color) & !pawn_attacks;
+
+    let mut score = Tapered::default();
+
+    for square in board.color_combined(color) & !board.pieces(Piece::Pawn) & !board.pieces(Piece::King) {
+        let piece = board.piece_on(square).unwrap();
+        let attacks = match piece {
+            Piece::Knight => get_knight_moves(square),
+            Piece::Bishop => get_bishop_moves(square, blockers),
+            Piece::Rook => get_rook_moves(square, blockers),
+            _ => get_bishop_moves(square, blockers) | get_rook_moves(square, blockers),
+        };
+
+        let idx = piece.to_index();
+        let mobility = (attacks & safe).popcnt() as i32 - params.mobility_base[idx];
+        score += params.mobility[idx] * mobility;
+
+        match piece {
+            Piece::Rook => {
+                let file = get_file(square.get_file());
+
+                if our_pawns & file == EMPTY {
+                    score += if their_pawns & file == EMPTY { params.rook_open_file } else { params.rook_semi_open_file };
+                }
+
+                // only worth it with pawns to eat or the king stuck on the back rank
+                if relative_rank(square, color) == 6
+                    && (their_pawns & get_rank(square.get_rank()) != EMPTY
+                        || relative_rank(board.king_square(!color), color) == 7)
+                {
+                    score += params.rook_on_seventh;
+                }
+            }
+            Piece::Knight => {
+                // supported by a pawn and no enemy pawn can ever chase it away
+                let rank = relative_rank(square, color);
+                let chasers = their_pawns & get_adjacent_files(square.get_file()) & forward_ranks(square, color);
+
+                if (3..=5).contains(&rank)
+                    && get_pawn_attacks(square, !color, our_pawns) != EMPTY
+                    && chasers == EMPTY
+                {
+                    score += params.knight_outpost;
+                }
+            }
+            Piece::Bishop => {
+                // own pawns stuck on the same colour as the bishop
+                let same_color = if BitBoard::from_square(square) & DARK_SQUARES == EMPTY {
+                    !DARK_SQUARES
+                } else {
+                    DARK_SQUARES
+                };
+                let pawns = (our_pawns & same_color).popcnt() as i32;
+                score += params.bad_bishop * pawns;
+            }
+            _ => {}
+        }
+    }
+
+    score
+}
+
+/// pieces of the opponent attacked by something cheaper, or not defended at all
+pub fn threats(board: &Board, color: Color, attacks: &[[BitBoard; 6]; 2], params: &EvalParams) -> Tapered {
+    let (ours, theirs) = (&attacks[color.to_index()], &attacks[(!color).to_index()]);
+    let (our_all, their_all) = (ours.iter().fold(EMPTY, |a, &b| a | b), theirs.iter().fold(EMPTY, |a, &b| a | b));
+
+    let pawns = board.pieces(Piece::Pawn);
+    let pieces = board.color_combined(!color) & !pawns & !board.pieces(Piece::King);
+    let majors = pieces & (board.pieces(Piece::Rook) | board.pieces(Piece::Queen));
+    let minor_attacks = ours[Piece::Knight.to_index()] | ours[Piece::Bishop.to_index()];
+
+    let mut score = params.threat_by_pawn * (pieces & ours[Piece::Pawn.to_index()]).popcnt() as i32;
+    score += params.threat_by_minor * (majors & minor_attacks).popcnt() as i32;
+    score += params.hanging_piece * (pieces & our_all & !their_all).popcnt() as i32;
+
+    // single pushes to squares the pawn isn't lost on, hitting pieces not already attacked by a pawn
+    let our_pawns = (board.color_combined(color) & pawns).0;
+    let pushes = BitBoard(if color == Color::White { our_pawns << 8 } else { our_pawns >> 8 })
+        & !board.combined()
+        & !theirs[Piece::Pawn.to_index()]
+        & (our_all | !their_all);
+
+    let mut push_attacks = EMPTY;
+    for square in pushes {
+        push_attacks |= get_pawn_attacks(square, color, !EMPTY);
+    }
+
+    score += params.pawn_push_threat * (pieces & push_attacks & !ours[Piece::Pawn.to_index()]).popcnt() as i32;
+
+    score
+}
+
+/// squares attacked by each piece type of `color`, shared by both sides' threats
+#[inline(always)]
+fn attacks(board: &Board, color: Color) -> [BitBoard; 6] {
+    let blockers = *board.combined();
+    let mut attacks = [EMPTY; 6];
+
+    for square in *board.color_combined(color) {
+        let piece = board.piece_on(square).unwrap();
+
+        attacks[piece.to_index()] |= match piece {
+            Piece::Pawn => get_pawn_attacks(square, color, !EMPTY),
+            Piece::Knight => get_knight_moves(square),
+            Piece::Bishop => get_bishop_moves(square, blockers),
+            Piece::Rook => get_rook_moves(square, blockers),
+            Piece::Queen => get_bishop_moves(square, blockers) | get_rook_moves(square, blockers),
+            Piece::King => get_king_moves(square),
+        };
+    }
+
+    attacks
+}
+
+#[inline(always)]
+fn rank_distance(a: Square, b: Square) -> usize {
+    a.get_rank().to_index().abs_diff(b.get_rank().to_index())
+}
+
+/// every rank in front of the square from the perspective of `color`
+#[inline(always)]
+fn forward_ranks(square: Square, color: Color) -> BitBoard {
+    let rank = square.get_rank().to_index();
+
+    BitBoard(match color {
+        Color::White => (!0_u64).checked_shl(8 * rank as u32 + 8).unwrap_or(0),
+        Color::Black => (1_u64 << (8 * rank)) - 1,
+    })
+}
+
+#[inline(always)]
+fn relative_rank(square: Square, color: Color) -> usize {
+    match color {
+        Color::White => square.get_rank().to_index(),
+        Color::Black => 7 - square.get_rank().to_index(),
+    }
+}
+
+#[inline(always)]
+fn relative_square(square: Square, color: Color) -> Square {
+    // invert square based on perspective
+    if matches!(color, Color::Black) {
+        unsafe { Square::new(square.to_int() ^ 63) }
+    } else {
+        square
+    }
+}
+
+// game phase contributed by each piece, the starting position adds up to `MAX_PHASE`
+const PHASE_WEIGHT: [i32; 6] = [0, 1, 1, 2, 4, 0];
+pub const MAX_PHASE: i32 = 24;
+
+pub const DARK_SQUARES: BitBoard = BitBoard(0xaa55_aa55_aa55_aa55);
 
 pub const MIN_EVAL: i32 = i32::MIN / 2;
 pub const MAX_EVAL: i32 = -MIN_EVAL;
+// evals past this are mates, the difference to `MAX_EVAL` being the distance in plies
+pub const MATE_THRESHOLD: i32 = MAX_EVAL - 1024;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    /// flips the board vertically and swaps the colours
+    fn mirror(fen: &str) -> String {
+        let fields: Vec<&str> = fen.split(' ').collect();
+        let swap_case = |s: &str| -> String {
+            s.chars().map(|c| if c.is_ascii_uppercase() { c.to_ascii_lowercase() } else { c.to_ascii_uppercase() }).collect()
+        };
+
+        let placement = fields[0].split('/').rev().map(swap_case).collect::<Vec<_>>().join("/");
+        let side = if fields[1] == "w" { "b" } else { "w" };
+        let castling = if fields[2] == "-" { "-".to_string() } else {
+            let swapped = swap_case(fields[2]);
+            let mut rights: Vec<char> = swapped.chars().collect();
+            rights.sort_by_key(|c| (c.is_ascii_lowercase(), *c != 'K' && *c != 'k'));
+            rights.into_iter().collect()
+        };
+        let en_passant = match fields[3].as_bytes() {
+            [file, rank] => format!("{}{}", *file as char, (b'1' + b'8' - rank) as char),
+            _ => "-".to_string(),
+        };
+
+        format!("{placement} {side} {castling} {en_passant} {} {}", fields[4], fields[5])
+    }
+
+    fn white_eval(board: &Board) -> i32 {
+        let eval = evaluate(board, &Accumulator::new(board, &EvalParams::default(), None), &Cache::with_capacity_mb(1));
+        if board.side_to_move() == Color::White { eval } else { -eval }
+    }
+
+    #[test]
+    fn mirrored_positions_are_negated() {
+        let positions = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "2r3k1/pp3ppp/4p3/3pP3/1P1P2Q1/P4N2/5PPP/R5K1 b - - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+            "8/8/4k3/3n4/8/2B5/4K3/8 w - - 0 1",
+        ];
+
+        for fen in positions {
+            let board = Board::from_str(fen).unwrap();
+            let mirrored = Board::from_str(&mirror(fen)).unwrap();
+
+            assert_eq!(white_eval(&board), -white_eval(&mirrored), "{fen}");
+        }
+    }
+
+    #[test]
+    fn trace_adds_up_to_the_eval() {
+        for fen in [
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+            "2r3k1/pp3ppp/4p3/3pP3/1P1P2Q1/P4N2/5PPP/R5K1 b - - 0 1",
+        ] {
+            let board = Board::from_str(fen).unwrap();
+            let trace = trace(&board, &Accumulator::new(&board, &EvalParams::default(), None), &Cache::with_capacity_mb(1));
+
+            assert_eq!(trace.total().taper(trace.phase, trace.scale), white_eval(&board), "{fen}");
+        }
+    }
+
+    #[test]
+    fn accumulator_follows_every_move() {
+        fn walk(board: &Board, acc: Accumulator, depth: usize) {
+            assert_eq!(acc, Accumulator::new(board, acc.params, None), "{board}");
+
+            if depth > 0 {
+                for m in MoveGen::new_legal(board) {
+                    walk(&board.make_move_new(m), acc.make_move(board, m), depth - 1);
+                }
+            }
+        }
+
+        // castling, en passant and promotions
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        ] {
+            let board = Board::from_str(fen).unwrap();
+            walk(&board, Accumulator::new(&board, &EvalParams::default(), None), 3);
+        }
+    }
+
+    /// the term is higher for white in `good` in at least one phase and no lower in the other
+    fn prefers_with(params: &EvalParams, f: fn(&Board, Color, &EvalParams) -> Tapered, good: &str, bad: &str) {
+        let term = |fen| f(&Board::from_str(fen).unwrap(), Color::White, params);
+        let (good, bad) = ((term(good).mid(), term(good).end()), (term(bad).mid(), term(bad).end()));
+        assert!(good.0 >= bad.0 && good.1 >= bad.1 && good != bad, "{good:?} {bad:?}");
+    }
+
+    fn prefers(f: fn(&Board, Color, &EvalParams) -> Tapered, good: &str, bad: &str) {
+        prefers_with(&EvalParams::default(), f, good, bad);
+    }
+
+    #[test]
+    fn pawn_structure_terms() {
+        let better = |good, bad| prefers(pawn_structure, good, bad);
+
+        // passed rather than blocked
+        better("4k3/8/8/3P4/8/8/8/4K3 w - - 0 1", "4k3/8/3p4/3P4/8/8/8/4K3 w - - 0 1");
+        // side by side rather than isolated
+        better("4k3/8/8/8/8/8/1PP5/4K3 w - - 0 1", "4k3/8/8/8/8/8/P1P5/4K3 w - - 0 1");
+        // split up rather than doubled, both isolated and passed either way
+        better("4k3/8/8/8/8/4P3/2P5/4K3 w - - 0 1", "4k3/8/8/8/8/2P5/2P5/4K3 w - - 0 1");
+        // d3 can't move up once e5 covers d4
+        better("4k3/8/4p3/8/2P5/3P4/8/4K3 w - - 0 1", "4k3/8/8/4p3/2P5/3P4/8/4K3 w - - 0 1");
+    }
+
+    #[test]
+    fn king_safety_terms() {
+        let better = |good, bad| prefers(king_safety, good, bad);
+
+        // shield pawns right in front of the king
+        better("6k1/8/8/8/8/8/5PPP/6K1 w - - 0 1", "6k1/8/8/8/8/6P1/5P1P/6K1 w - - 0 1");
+        // no open file next to the king
+        better("6k1/8/8/8/8/6P1/5P1P/6K1 w - - 0 1", "6k1/8/8/8/8/8/5P1P/6K1 w - - 0 1");
+        // enemy pawns storming up are worse the closer they get
+        better("6k1/6p1/8/8/8/8/5PPP/6K1 w - - 0 1", "6k1/8/8/8/6p1/8/5PPP/6K1 w - - 0 1");
+        // queen and knight both hitting the king zone
+        better("q5k1/n7/8/8/8/8/5PPP/6K1 w - - 0 1", "6k1/8/8/8/5n1q/8/5PPP/6K1 w - - 0 1");
+    }
+
+    #[test]
+    fn piece_activity_terms() {
+        // more squares to go to
+        prefers(piece_activity, "4k3/8/8/8/3N4/8/8/4K3 w - - 0 1", "4k3/8/8/8/8/8/8/N3K3 w - - 0 1");
+
+        // the rest without mobility getting in the way
+        let params = EvalParams { mobility: [Tapered::default(); 6], ..EvalParams::default() };
+        let better = |good, bad| prefers_with(&params, piece_activity, good, bad);
+
+        // open, then semi open, then closed files for rooks
+        better("4k3/8/8/8/8/8/8/3RK3 w - - 0 1", "4k3/8/3p4/8/8/8/8/3RK3 w - - 0 1");
+        better("4k3/8/3p4/8/8/8/8/3RK3 w - - 0 1", "4k3/8/8/8/8/3P4/8/3RK3 w - - 0 1");
+        // rook on the 7th with pawns to eat
+        better("4k3/p2R4/8/8/8/8/8/4K3 w - - 0 1", "4k3/p7/3R4/8/8/8/8/4K3 w - - 0 1");
+        // supported knight no pawn can chase away
+        better("4k3/8/8/4N3/3P4/8/8/4K3 w - - 0 1", "4k3/5p2/8/4N3/3P4/8/8/4K3 w - - 0 1");
+        // own pawns off the bishop's colour
+        better("4k3/8/8/8/8/3P4/8/2B1K3 w - - 0 1", "4k3/8/8/8/8/4P3/8/2B1K3 w - - 0 1");
+    }
+
+    /// picks the move with the best static eval and checks whether the reply wins a piece,
+    /// counting a recapture on the same square
+    fn blunders(params: &EvalParams) -> usize {
+        let value = |piece: Option<Piece>| piece.and_then(|p| params.piece_value.get(p.to_index()).copied()).unwrap_or(0);
+        let pawn_table = Cache::with_capacity_mb(1);
+
+        TACTICS.iter().filter(|fen| {
+            let board = Board::from_str(fen).unwrap();
+            let best = MoveGen::new_legal(&board).max_by_key(|&m| {
+                let after = board.make_move_new(m);
+                -evaluate(&after, &Accumulator::new(&after, params, None), &pawn_table)
+            }).unwrap();
+
+            let after = board.make_move_new(best);
+
+            MoveGen::new_legal(&after).any(|m| {
+                let Some(victim) = after.piece_on(m.get_dest()) else { return false };
+                let recapture = MoveGen::new_legal(&after.make_move_new(m)).any(|r| r.get_dest() == m.get_dest());
+                let gain = value(Some(victim)) - if recapture { value(after.piece_on(m.get_source())) } else { 0 };
+
+                gain >= params.piece_value[1] - params.piece_value[0]
+            })
+        }).count()
+    }
+
+    #[test]
+    fn threats_prevent_one_move_blunders() {
+        let params = EvalParams::default();
+        let blind = EvalParams {
+            threat_by_pawn: Tapered::default(),
+            threat_by_minor: Tapered::default(),
+            hanging_piece: Tapered::default(),
+            pawn_push_threat: Tapered::default(),
+            ..params
+        };
 
-// a1 ----> h1
-// |
-// v
-// a8
-//
-// value mostly from https://www.chessprogramming.org/Simplified_Evaluation_Function
-// pawn endgame was added by me
-const PIECE_SQUARE_TABLE_MID: [i32; 64 * 6] = [
-    // Pawn
-      0,   0,   0,   0,   0,   0,   0,   0,
-      5,  10,  10, -20, -20,  10,  10,   5,
-      5,  -5, -10,   0,   0, -10,  -5,   5,
-      0,   0,   0,  20,  20,   0,   0,   0,
-      5,   5,  10,  25,  25,  10,   5,   5,
-     10,  10,  20,  30,  30,  20,  10,  10,
-     50,  50,  50,  50,  50,  50,  50,  50,
-      0,   0,   0,   0,   0,   0,   0,   0,
-    // Knight
-    -50, -40, -30, -30, -30, -30, -40, -50,
-    -40, -20,   0,   5,   5,   0, -20, -40,
-    -30,   5,  10,  15,  15,  10,   5, -30,
-    -30,   0,  15,  20,  20,  15,   0, -30,
-    -30,   5,  15,  20,  20,  15,   5, -30,
-    -30,   0,  10,  15,  15,  10,   0, -30,
-    -40, -20,   0,   0,   0,   0, -20, -40,
-    -50, -40, -30, -30, -30, -30, -40, -50,
-    // Bishop
-    -20, -10, -10, -10, -10, -10, -10, -20,
-    -10,   5,   0,   0,   0,   0,   5, -10,
-    -10,  10,  10,  10,  10,  10,  10, -10,
-    -10,   0,  10,  10,  10,  10,   0, -10,
-    -10,   5,   5,  10,  10,   5,   5, -10,
-    -10,   0,   5,  10,  10,   5,   0, -10,
-    -10,   0,   0,   0,   0,   0,   0, -10,
-    -20, -10, -10, -10, -10, -10, -10, -20,
-    // Rook
-      0,   0,   0,   5,   5,   0,   0,   0,
-     -5,   0,   0,   0,   0,   0,   0,  -5,
-     -5,   0,   0,   0,   0,   0,   0,  -5,
-     -5,   0,   0,   0,   0,   0,   0,  -5,
-     -5,   0,   0,   0,   0,   0,   0,  -5,
-     -5,   0,   0,   0,   0,   0,   0,  -5,
-      5,  10,  10,  10,  10,  10,  10,   5,
-      0,   0,   0,   0,   0,   0,   0,   0,
-    // Queen
-    -20, -10, -10,  -5,  -5, -10, -10, -20,
-    -10,   0,   5,   0,   0,   0,   0, -10,
-    -10,   5,   5,   5,   5,   5,   0, -10,
-      0,   0,   5,   5,   5,   5,   0,  -5,
-     -5,   0,   5,   5,   5,   5,   0,  -5,
-    -10,   0,   5,   5,   5,   5,   0, -10,
-    -10,   0,   0,   0,   0,   0,   0, -10,
-    -20, -10, -10,  -5,  -5, -10, -10, -20,
-    // King
-     20,  30,  10,   0,   0,  10,  30,  20,
-     20,  20,   0,   0,   0,   0,  20,  20,
-    -10, -20, -20, -20, -20, -20, -20, -10,
-    -20, -30, -30, -40, -40, -30, -30, -20,
-    -30, -40, -40, -50, -50, -40, -40, -30,
-    -30, -40, -40, -50, -50, -40, -40, -30,
-    -30, -40, -40, -50, -50, -40, -40, -30,
-    -30, -40, -40, -50, -50, -40, -40, -30,
-];
-
-const PIECE_SQUARE_TABLE_END: [i32; 64 * 6] = [
-    // Pawn
-      0,   0,   0,   0,   0,   0,   0,   0,
-    -20, -20, -20, -20, -20, -20, -20, -20,
-    -10, -10, -10, -10, -10, -10, -10, -10,
-     20,  20,  20,  20,  20,  20,  20,  20,
-     30,  30,  30,  30,  30,  30,  30,  30,
-     40,  40,  40,  40,  40,  40,  40,  40,
-     50,  50,  50,  50,  50,  50,  50,  50,
-      0,   0,   0,   0,   0,   0,   0,   0,
-    // Knight
-    -50, -40, -30, -30, -30, -30, -40, -50,
-    -40, -20,   0,   5,   5,   0, -20, -40,
-    -30,   5,  10,  15,  15,  10,   5, -30,
-    -30,   0,  15,  20,  20,  15,   0, -30,
-    -30,   5,  15,  20,  20,  15,   5, -30,
-    -30,   0,  10,  15,  15,  10,   0, -30,
-    -40, -20,   0,   0,   0,   0, -20, -40,
-    -50, -40, -30, -30, -30, -30, -40, -50,
-    // Bishop
-    -20, -10, -10, -10, -10, -10, -10, -20,
-    -10,   5,   0,   0,   0,   0,   5, -10,
-    -10,  10,  10,  10,  10,  10,  10, -10,
-    -10,   0,  10,  10,  10,  10,   0, -10,
-    -10,   5,   5,  10,  10,   5,   5, -10,
-    -10,   0,   5,  10,  10,   5,   0, -10,
-    -10,   0,   0,   0,   0,   0,   0, -10,
-    -20, -10, -10, -10, -10, -10, -10, -20,
-    // Rook
-      0,   0,   0,   5,   5,   0,   0,   0,
-     -5,   0,   0,   0,   0,   0,   0,  -5,
-     -5,   0,   0,   0,   0,   0,   0,  -5,
-     -5,   0,   0,   0,   0,   0,   0,  -5,
-     -5,   0,   0,   0,   0,   0,   0,  -5,
-     -5,   0,   0,   0,   0,   0,   0,  -5,
-      5,  10,  10,  10,  10,  10,  10,   5,
-      0,   0,   0,   0,   0,   0,   0,   0,
-    // Queen
-    -20, -10, -10,  -5,  -5, -10, -10, -20,
-    -10,   0,   5,   0,   0,   0,   0, -10,
-    -10,   5,   5,   5,   5,   5,   0, -10,
-      0,   0,   5,   5,   5,   5,   0,  -5,
-     -5,   0,   5,   5,   5,   5,   0,  -5,
-    -10,   0,   5,   5,   5,   5,   0, -10,
-    -10,   0,   0,   0,   0,   0,   0, -10,
-    -20, -10, -10,  -5,  -5, -10, -10, -20,
-    // King
-    -50, -30, -30, -30, -30, -30, -30, -50,
-    -30, -30,   0,   0,   0,   0, -30, -30,
-    -30, -10,  20,  30,  30,  20, -10, -30,
-    -30, -10,  30,  40,  40,  30, -10, -30,
-    -30, -10,  30,  40,  40,  30, -10, -30,
-    -30, -10,  20,  30,  30,  20, -10, -30,
-    -30, -20, -10,   0,   0, -10, -20, -30,
-    -50, -40, -30, -20, -20, -30, -40, -50,
-];
+        let (with, without) = (blunders(&params), blunders(&blind));
+        assert!(with < without, "{with} blunders with threats, {without} without");
+    }
+
+    #[test]
+    fn tapered_packs_both_halves() {
+        for (mid, end) in [(0, 0), (5, -8), (-300, 250), (-1, -1), (4000, -4000)] {
+            let score = Tapered::new(mid, end);
+            assert_eq!((score.mid(), score.end()), (mid, end));
+            assert_eq!(((-score).mid(), (-score).end()), (-mid, -end));
+            assert_eq!(((score * 3).mid(), (score * 3).end()), (mid * 3, end * 3));
+        }
+
+        assert_eq!(Tapered::new(100, 0).taper(MAX_PHASE, SCALE_NORMAL), 100);
+        assert_eq!(Tapered::new(100, 0).taper(0, SCALE_NORMAL), 0);
+        assert_eq!(Tapered::new(0, 100).taper(0, SCALE_NORMAL / 2), 50);
+    }
+
+    // a piece of the side to move is attacked by something cheaper or left undefended, or there's
+    // a capture that walks into one
+    const TACTICS: [&str; 10] = [
+        "rnbqkb1r/pppppppp/5n2/4P3/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2",
+        "r1bqkbnr/1ppp1ppp/p1n5/1B2p3/4P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 0 4",
+        "rnb1kbnr/pppp1ppp/8/4p3/4P2q/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 3",
+        "rnbqkb1r/pppp1ppp/8/4p3/4P1n1/3P4/PPP2PPP/RNBQKBNR b KQkq - 0 3",
+        "4k3/pp3ppp/8/8/3r4/4B3/PPP2PPP/4K3 b - - 0 1",
+        "4k3/pp3ppp/8/8/2r5/1P6/P4PPP/4K3 b - - 0 1",
+        "r1bqkbnr/pppp1ppp/2n5/3Pp3/4P3/8/PPP2PPP/RNBQKBNR b KQkq - 0 3",
+        "rn1qkbnr/ppp1pppp/8/3p4/3P2b1/5P2/PPP1P1PP/RNBQKBNR b KQkq - 0 3",
+        "r1bqkb1r/pppp1ppp/2n5/4p3/2B1n3/2N2N2/PPPP1PPP/R1BQK2R w KQkq - 0 5",
+        "rnbqk2r/pppp1ppp/5n2/2b1p3/4P3/3P1N2/PPP2PPP/RNBQKB1R w KQkq - 1 4",
+    ];
+}
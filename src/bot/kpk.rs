@@ -0,0 +1,234 @@
+use chess::*;
+use std::sync::OnceLock;
+
+// white is always the side with the pawn, and the pawn is mirrored onto files a to d
+const SIZE: usize = 2 * 24 * 64 * 64;
+
+// results while building, combined as bit flags when looking at every move
+const INVALID: u8 = 0;
+const UNKNOWN: u8 = 1;
+const DRAW: u8 = 2;
+const WIN: u8 = 4;
+
+static BITBASE: OnceLock<Box<[u64]>> = OnceLock::new();
+
+/// builds the bitbase if it isn't already, takes a few milliseconds
+pub fn init() {
+    BITBASE.get_or_init(build);
+}
+
+/// whether the side with the pawn wins with best play
+pub fn probe(strong: Color, strong_king: Square, pawn: Square, weak_king: Square, side_to_move: Color) -> bool {
+    let normalize = |square: Square| {
+        let mut index = square.to_index();
+
+        if strong == Color::Black {
+            index ^= 56;
+        }
+
+        if pawn.get_file().to_index() >= 4 {
+            index ^= 7;
+        }
+
+        index
+    };
+
+    let idx = index(side_to_move == strong, normalize(weak_king), normalize(strong_king), normalize(pawn));
+    let bitbase = BITBASE.get_or_init(build);
+
+    bitbase[idx / 64] & (1 << (idx % 64)) != 0
+}
+
+#[inline(always)]
+const fn index(white_to_move: bool, black_king: usize, white_king: usize, pawn: usize) -> usize {
+    let (file, rank) = (pawn % 8, pawn / 8);
+    white_king | black_king << 6 | (!white_to_move as usize) << 12 | file << 13 | (6 - rank) << 15
+}
+
+/// retrograde analysis, every position starts out as known or unknown and unknown ones are
+/// resolved from their children until nothing changes
+fn build() -> Box<[u64]> {
+    let mut results = vec![INVALID; SIZE];
+    let mut unknown = Vec::new();
+
+    for white_to_move in [true, false] {
+        for pawn in (8..56).filter(|p| p % 8 < 4) {
+            for white_king in 0..64 {
+                for black_king in 0..64 {
+                    let idx = index(white_to_move, black_king, white_king, pawn);
+                    results[idx] = classify_leaf(white_to_move, black_king, white_king, pawn);
+
+                    if results[idx] == UNKNOWN {
+                        unknown.push((idx, white_to_move, black_king, white_king, pawn));
+                    }
+                }
+            }
+        }
+    }
+
+    let mut changed = true;
+
+    while changed {
+        changed = false;
+
+        unknown.retain(|&(idx, white_to_move, black_king, white_king, pawn)| {
+            let result = classify(&results, white_to_move, black_king, white_king, pawn);
+            results[idx] = result;
+            changed |= result != UNKNOWN;
+            result == UNKNOWN
+        });
+    }
+
+    let mut bitbase = vec![0_u64; SIZE / 64].into_boxed_slice();
+
+    for (idx, result) in results.into_iter().enumerate() {
+        if result == WIN {
+            bitbase[idx / 64] |= 1 << (idx % 64);
+        }
+    }
+
+    bitbase
+}
+
+fn classify_leaf(white_to_move: bool, black_king: usize, white_king: usize, pawn: usize) -> u8 {
+    let (bk, wk, p) = (square(black_king), square(white_king), square(pawn));
+    let pawn_attacks = get_pawn_attacks(p, Color::White, !EMPTY);
+
+    // kings touching, pieces on top of each other or black in check with white to move
+    if distance(wk, bk) <= 1
+        || white_king == pawn
+        || black_king == pawn
+        || (white_to_move && pawn_attacks & BitBoard::from_square(bk) != EMPTY)
+    {
+        return INVALID;
+    }
+
+    if white_to_move && pawn / 8 == 6 {
+        let promotion = square(pawn + 8);
+
+        // promotes safely, the new queen is either out of reach or protected
+        if white_king != pawn + 8
+            && black_king != pawn + 8
+            && (distance(bk, promotion) > 1 || distance(wk, promotion) == 1)
+        {
+            return WIN;
+        }
+    }
+
+    if !white_to_move {
+        let escapes = get_king_moves(bk) & !get_king_moves(wk) & !pawn_attacks;
+
+        // stalemate, or the pawn is hanging
+        if escapes == EMPTY
+            || (get_king_moves(bk) & BitBoard::from_square(p) != EMPTY && get_king_moves(wk) & BitBoard::from_square(p) == EMPTY)
+        {
+            return DRAW;
+        }
+    }
+
+    UNKNOWN
+}
+
+fn classify(results: &[u8], white_to_move: bool, black_king: usize, white_king: usize, pawn: usize) -> u8 {
+    let mut r = INVALID;
+
+    if white_to_move {
+        for to in get_king_moves(square(white_king)) {
+            r |= results[index(false, black_king, to.to_index(), pawn)];
+        }
+
+        if pawn / 8 < 6 {
+            r |= results[index(false, black_king, white_king, pawn + 8)];
+        }
+
+        if pawn / 8 == 1 && pawn + 8 != white_king && pawn + 8 != black_king {
+            r |= results[index(false, black_king, white_king, pawn + 16)];
+        }
+
+        if r & WIN != 0 { WIN } else if r & UNKNOWN != 0 { UNKNOWN } else { DRAW }
+    } else {
+        for to in get_king_moves(square(black_king)) {
+            r |= results[index(true, to.to_index(), white_king, pawn)];
+        }
+
+        if r & DRAW != 0 { DRAW } else if r & UNKNOWN != 0 { UNKNOWN } else { WIN }
+    }
+}
+
+#[inline(always)]
+fn square(index: usize) -> Square {
+    unsafe { Square::new(index as u8) }
+}
+
+#[inline(always)]
+fn distance(a: Square, b: Square) -> usize {
+    let files = a.get_file().to_index().abs_diff(b.get_file().to_index());
+    let ranks = a.get_rank().to_index().abs_diff(b.get_rank().to_index());
+    files.max(ranks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn wins(fen: &str) -> bool {
+        let board = Board::from_str(fen).unwrap();
+        let pawns = board.pieces(Piece::Pawn);
+        let strong = if board.color_combined(Color::White) & pawns != EMPTY { Color::White } else { Color::Black };
+
+        probe(strong, board.king_square(strong), pawns.to_square(), board.king_square(!strong), board.side_to_move())
+    }
+
+    #[test]
+    fn known_positions() {
+        // opposition decides it
+        assert!(!wins("8/3k4/8/3K4/3P4/8/8/8 w - - 0 1"));
+        assert!(wins("8/3k4/8/3K4/3P4/8/8/8 b - - 0 1"));
+
+        // a king on the sixth in front of the pawn always wins
+        assert!(wins("3k4/8/3K4/3P4/8/8/8/8 w - - 0 1"));
+        assert!(wins("3k4/8/3K4/3P4/8/8/8/8 b - - 0 1"));
+
+        // the defending king is in front of the pawn in time
+        assert!(!wins("8/8/8/4k3/8/8/4P3/4K3 w - - 0 1"));
+
+        // outside the square, the pawn runs
+        assert!(wins("7k/8/8/8/P7/8/8/K7 w - - 0 1"));
+        assert!(wins("7k/8/8/8/P7/8/8/K7 b - - 0 1"));
+        assert!(!wins("8/8/8/8/P3k3/8/8/K7 b - - 0 1"));
+
+        // rook pawns are drawn once the king reaches the corner
+        assert!(!wins("k7/8/8/8/8/8/P7/K7 w - - 0 1"));
+        assert!(!wins("k7/8/1K6/P7/8/8/8/8 w - - 0 1"));
+        assert!(!wins("7k/8/6K1/7P/8/8/8/8 b - - 0 1"));
+
+        // the same with black as the stronger side
+        assert!(!wins("8/8/8/3p4/3k4/8/3K4/8 b - - 0 1"));
+        assert!(wins("8/8/8/3p4/3k4/8/3K4/8 w - - 0 1"));
+    }
+
+    #[test]
+    fn builds_the_same_every_time() {
+        let start = std::time::Instant::now();
+        let bitbase = build();
+
+        // a few milliseconds in release, the budget only catches a build that stopped converging
+        assert!(start.elapsed() < std::time::Duration::from_secs(5), "took {:?}", start.elapsed());
+        assert_eq!(&bitbase, BITBASE.get_or_init(build));
+
+        // probed straight from the fresh build, white to move with the pawn on files a to d
+        let wins = |white_to_move, black_king: Square, white_king: Square, pawn: Square| {
+            let idx = index(white_to_move, black_king.to_index(), white_king.to_index(), pawn.to_index());
+            bitbase[idx / 64] & (1 << (idx % 64)) != 0
+        };
+
+        assert!(wins(true, Square::D8, Square::D6, Square::D5));
+        assert!(wins(false, Square::D7, Square::D5, Square::D4));
+        assert!(!wins(true, Square::D7, Square::D5, Square::D4));
+        assert!(wins(true, Square::H8, Square::A1, Square::A4));
+        assert!(!wins(true, Square::A8, Square::A1, Square::A2));
+        assert!(!wins(true, Square::A8, Square::B6, Square::A5));
+        assert!(!wins(true, Square::D5, Square::D1, Square::D2));
+    }
+}
@@ -1,12 +1,26 @@
 mod eval;
+mod endgame;
 mod search;
+pub mod book;
+// command line tools, only built along with the uci client
+#[cfg(feature = "uci")]
+pub mod book_build;
+pub mod cache;
+pub mod kpk;
+pub mod nnue;
+pub mod params;
+#[cfg(feature = "uci")]
+pub mod tune;
 pub mod trans_table;
 pub mod config;
+pub mod result;
 
-use std::sync::atomic::*;
+use std::sync::{atomic::*, Arc};
 use chess::*;
 use std::time::*;
 
+pub use result::*;
+
 pub struct Game {
     pub board: chess::Board,
     pub moves: Vec<ChessMove>,
@@ -14,11 +28,30 @@ pub struct Game {
     pub trans_table: trans_table::TransTable,
     pub age: usize,
 
+    pub eval_cache: cache::Cache,
+    pub pawn_table: cache::Cache,
+    pub params: Arc<params::EvalParams>,
+    // classical eval when there's none
+    pub network: Option<Arc<nnue::Network>>,
+    // centipawns a draw costs the side to move at the root, positive to avoid them
+    pub contempt: i32,
+    // root side and contempt the draw scores in the transposition table were searched with
+    pub draw_scores: Option<(Color, i32)>,
+
+    pub book: Option<Arc<book::Book>>,
+    pub book_selection: book::Selection,
+    // plies from the start of the game the book is used for
+    pub book_depth: usize,
+
     pub time_ctrl: TimeControl,
     pub time_ref: Instant,
     pub time_usable: Duration,
+    pub limits: SearchLimits,
 
     pub searched: AtomicUsize,
+    pub seldepth: AtomicUsize,
+
+    pub pool: Arc<rayon::ThreadPool>,
 }
 
 // #[derive(Debug)]
@@ -48,33 +81,181 @@ pub struct TimeControl {
     pub time_incr: usize,
 }
 
+#[derive(Debug, Clone, Copy)]
+pub struct SearchLimits {
+    pub depth: usize,
+    pub nodes: usize,
+}
+
+impl Default for SearchLimits {
+    fn default() -> Self {
+        Self {
+            depth: config::MAX_SEARCH_DEPTH,
+            nodes: usize::MAX,
+        }
+    }
+}
+
 impl Game {
+    #[cfg(feature = "uci")]
     pub fn new(board: Board, moves: Vec<ChessMove>) -> Self {
-        Game {
+        Self::with_hash_size(board, moves, config::DEFAULT_HASH_MB)
+    }
+
+    pub fn with_hash_size(board: Board, moves: Vec<ChessMove>, hash_mb: usize) -> Self {
+        Self {
             board,
             moves,
 
-            trans_table: trans_table::TransTable::new(),
+            trans_table: trans_table::TransTable::with_capacity_mb(hash_mb),
             age: 1,
 
+            eval_cache: cache::Cache::with_capacity_mb(config::EVAL_CACHE_MB),
+            pawn_table: cache::Cache::with_capacity_mb(config::PAWN_TABLE_MB),
+            params: Arc::new(params::EvalParams::default()),
+            network: None,
+            contempt: config::DEFAULT_CONTEMPT,
+            draw_scores: None,
+
+            book: None,
+            book_selection: book::Selection::default(),
+            book_depth: config::DEFAULT_BOOK_DEPTH,
+
             time_ctrl: TimeControl::default(),
             time_ref: Instant::now(),
             time_usable: Duration::from_secs(0),
+            limits: SearchLimits::default(),
 
             searched: AtomicUsize::new(0),
+            seldepth: AtomicUsize::new(0),
+
+            pool: shared_pool(),
         }
     }
 
-    pub fn play(&mut self) -> ChessMove {
+    fn build_pool(threads: usize) -> rayon::ThreadPool {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .stack_size(config::THREAD_STACK_SIZE)
+            .thread_name(|i| format!("search_thread_{i}"))
+            .build().unwrap()
+    }
+
+    #[cfg(feature = "uci")]
+    pub fn set_threads(&mut self, threads: usize) {
+        if threads.max(1) != self.threads() {
+            self.pool = Arc::new(Self::build_pool(threads.max(1)));
+        }
+    }
+
+    #[cfg(feature = "uci")]
+    pub fn set_hash_size(&mut self, mb: usize) {
+        self.trans_table.resize(mb.max(1));
+        info!("transposition table resized to {} MB", self.trans_table.size_mb());
+    }
+
+    /// cached evals from the other evaluator are no good anymore
+    #[cfg(feature = "uci")]
+    pub fn set_network(&mut self, network: Option<Arc<nnue::Network>>) {
+        self.network = network;
+        self.clear_hash();
+    }
+
+    /// pawn structure and evals cached with the old weights are no good anymore either
+    #[cfg(feature = "uci")]
+    pub fn set_params(&mut self, params: params::EvalParams) {
+        self.params = Arc::new(params);
+        self.clear_hash();
+    }
+
+    #[cfg(feature = "uci")]
+    pub fn clear_hash(&self) {
+        self.pool.install(|| {
+            self.trans_table.clear();
+            self.eval_cache.clear();
+            self.pawn_table.clear();
+        });
+    }
+
+    /// static eval from the side to move's perspective
+    pub fn evaluate(&self, board: &Board, acc: &eval::Accumulator) -> i32 {
+        self.cached_eval(board).unwrap_or_else(|| self.evaluate_uncached(board, acc))
+    }
+
+    /// lets callers skip building an accumulator when the eval is already known
+    pub fn cached_eval(&self, board: &Board) -> Option<i32> {
+        self.eval_cache.get(board.get_hash()).map(|eval| eval as i32)
+    }
+
+    pub fn evaluate_uncached(&self, board: &Board, acc: &eval::Accumulator) -> i32 {
+        let eval = eval::evaluate(board, acc, &self.pawn_table);
+        self.eval_cache.insert(board.get_hash(), eval as u32 as u64);
+        eval
+    }
+
+    /// per term breakdown of the eval of the current board
+    #[cfg(feature = "uci")]
+    pub fn eval_trace(&self) -> eval::EvalTrace {
+        let acc = eval::Accumulator::new(&self.board, &self.params, self.network.as_deref());
+        eval::trace(&self.board, &acc, &self.pawn_table)
+    }
+
+    #[cfg(feature = "uci")]
+    pub fn save_hash(&self, path: &str) -> std::io::Result<()> {
+        self.trans_table.save(path)
+    }
+
+    #[cfg(feature = "uci")]
+    pub fn load_hash(&self, path: &str) -> std::io::Result<()> {
+        self.trans_table.load(path, self.age)
+    }
+
+    pub fn threads(&self) -> usize {
+        self.pool.current_num_threads()
+    }
+
+    /// with a single thread the root moves are searched in order, so the same position and limits
+    /// always give the same node counts and best move
+    pub fn is_deterministic(&self) -> bool {
+        self.threads() == 1
+    }
+
+    pub fn play(&mut self) -> SearchResult {
         self.time_ref = Instant::now();
         self.age += 1;
+
+        if let Some(m) = self.book_move() {
+            info!("next move: {} (book)", m);
+            self.board = self.board.make_move_new(m);
+            self.moves.push(m);
+
+            return SearchResult::from_book(m, self.time_ref.elapsed());
+        }
+
+        // contempt draws are stored from the root side's point of view, so they flip with it
+        let draw_scores = (self.contempt != 0).then_some((self.board.side_to_move(), self.contempt));
+        if self.draw_scores.is_some() && self.draw_scores != draw_scores {
+            self.pool.install(|| self.trans_table.clear());
+        }
+        self.draw_scores = draw_scores;
+
         info!("start search");
         self.reserve_time();
-        let (next, eval) = self.search();
-        info!("next move: {} (eval: {})", next, eval);
-        self.board = self.board.make_move_new(next);
+        let result = self.search();
+
+        info!("next move: {} (score: {}, depth: {}, pv: {})", result.best_move, result.score, result.depth, result.pv_string());
+        self.board = self.board.make_move_new(result.best_move);
+        self.moves.push(result.best_move);
+
+        result
+    }
+
+    fn book_move(&self) -> Option<ChessMove> {
+        if self.moves.len() >= self.book_depth {
+            return None;
+        }
 
-        next
+        self.book.as_ref()?.pick(&self.board, self.book_selection)
     }
 
     // pub fn play(&mut self) {
@@ -141,6 +322,11 @@ impl Game {
     // }
 
     pub fn reserve_time(&mut self) {
+        if self.time_ctrl.time_left == usize::MAX {
+            self.time_usable = Duration::MAX;
+            return;
+        }
+
         // https://github.com/SebLague/Chess-Coding-Adventure/blob/Chess-V2-UCI/Chess-Coding-Adventure/src/Bot.cs#L64
 
         let left = self.time_ctrl.time_left as u64;
@@ -157,6 +343,19 @@ impl Game {
     }
 
     pub fn times_up(&self) -> bool {
-        self.time_ref.elapsed() > self.time_usable
+        self.searched.load(Ordering::Relaxed) >= self.limits.nodes
+            || self.time_ref.elapsed() > self.time_usable
     }
 }
+
+pub fn default_threads() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+static POOL: std::sync::OnceLock<Arc<rayon::ThreadPool>> = std::sync::OnceLock::new();
+
+/// every game starts out on this one, so games searching at the same time split the cores
+/// instead of each running a pool as wide as the machine
+pub fn shared_pool() -> Arc<rayon::ThreadPool> {
+    POOL.get_or_init(|| Arc::new(Game::build_pool(default_threads()))).clone()
+}
@@ -0,0 +1,209 @@
+use chess::*;
+use std::io;
+
+// (768 -> HIDDEN)x2 -> 1 with screlu, quantised the way bullet exports its simple networks
+pub const INPUTS: usize = 768;
+pub const HIDDEN: usize = 256;
+
+const QA: i32 = 255;
+const QB: i32 = 64;
+const SCALE: i32 = 400;
+
+/// weights as stored in the network file, every value a little endian `i16` in this order
+pub struct Network {
+    feature_weights: Box<[i16]>, // INPUTS * HIDDEN
+    feature_bias: Box<[i16]>,    // HIDDEN
+    output_weights: Box<[i16]>,  // 2 * HIDDEN, side to move first
+    output_bias: i16,
+}
+
+impl Network {
+    pub const FILE_SIZE: usize = (INPUTS * HIDDEN + HIDDEN + 2 * HIDDEN + 1) * 2;
+
+    pub fn load(path: &str) -> io::Result<Self> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    /// trailing padding is allowed since some trainers align the file to 64 bytes
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        if bytes.len() < Self::FILE_SIZE || bytes.len() - Self::FILE_SIZE >= 64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("expected a {} byte network, got {} bytes", Self::FILE_SIZE, bytes.len()),
+            ));
+        }
+
+        let mut values = bytes.chunks_exact(2).map(|b| i16::from_le_bytes([b[0], b[1]]));
+        let mut take = |n: usize| values.by_ref().take(n).collect::<Box<[i16]>>();
+
+        let feature_weights = take(INPUTS * HIDDEN);
+        let feature_bias = take(HIDDEN);
+        let output_weights = take(2 * HIDDEN);
+        let output_bias = take(1)[0];
+
+        Ok(Self { feature_weights, feature_bias, output_weights, output_bias })
+    }
+
+    /// from the side to move's perspective, in centipawns
+    pub fn evaluate(&self, acc: &Accumulator, side: Color) -> i32 {
+        let (ours, theirs) = self.output_weights.split_at(HIDDEN);
+
+        let sum = screlu_dot(&acc.values[side.to_index()], ours)
+            + screlu_dot(&acc.values[(!side).to_index()], theirs);
+
+        (sum / QA + self.output_bias as i32) * SCALE / (QA * QB)
+    }
+
+    #[inline(always)]
+    fn weights(&self, feature: usize) -> &[i16] {
+        &self.feature_weights[feature * HIDDEN..(feature + 1) * HIDDEN]
+    }
+}
+
+impl std::fmt::Debug for Network {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Network {{ {INPUTS} -> {HIDDEN}x2 -> 1 }}")
+    }
+}
+
+// kept as plain loops over fixed size slices so they get auto-vectorised
+#[inline(always)]
+fn screlu_dot(values: &[i16; HIDDEN], weights: &[i16]) -> i32 {
+    values.iter().zip(weights).map(|(&v, &w)| {
+        let v = (v as i32).clamp(0, QA);
+        v * v * w as i32
+    }).sum()
+}
+
+/// hidden layer of both perspectives, updated piece by piece as moves are made
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Accumulator {
+    values: [[i16; HIDDEN]; 2],
+}
+
+impl std::fmt::Debug for Accumulator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Accumulator {{ white: {:?}.., black: {:?}.. }}", &self.values[0][..4], &self.values[1][..4])
+    }
+}
+
+impl Accumulator {
+    pub fn new(network: &Network, board: &Board) -> Self {
+        let mut bias = [0; HIDDEN];
+        bias.copy_from_slice(&network.feature_bias);
+
+        let mut acc = Self { values: [bias; 2] };
+
+        for square in *board.combined() {
+            acc.add(network, board.piece_on(square).unwrap(), board.color_on(square).unwrap(), square);
+        }
+
+        acc
+    }
+
+    #[inline(always)]
+    pub fn add(&mut self, network: &Network, piece: Piece, color: Color, square: Square) {
+        for perspective in ALL_COLORS {
+            let weights = network.weights(feature(perspective, piece, color, square));
+
+            for (v, &w) in self.values[perspective.to_index()].iter_mut().zip(weights) {
+                *v = v.wrapping_add(w);
+            }
+        }
+    }
+
+    #[inline(always)]
+    pub fn remove(&mut self, network: &Network, piece: Piece, color: Color, square: Square) {
+        for perspective in ALL_COLORS {
+            let weights = network.weights(feature(perspective, piece, color, square));
+
+            for (v, &w) in self.values[perspective.to_index()].iter_mut().zip(weights) {
+                *v = v.wrapping_sub(w);
+            }
+        }
+    }
+}
+
+/// own pieces come first and black sees the board flipped
+#[inline(always)]
+fn feature(perspective: Color, piece: Piece, color: Color, square: Square) -> usize {
+    let (side, square) = match perspective {
+        Color::White => (color.to_index(), square.to_index()),
+        Color::Black => ((!color).to_index(), square.to_index() ^ 56),
+    };
+
+    side * 384 + piece.to_index() * 64 + square
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::eval;
+    use super::super::params::EvalParams;
+    use std::str::FromStr;
+
+    fn random_network() -> Network {
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let bytes = (0..Network::FILE_SIZE / 2).flat_map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            ((state % 255) as i16 - 127).to_le_bytes()
+        }).collect::<Vec<_>>();
+
+        Network::from_bytes(&bytes).unwrap()
+    }
+
+    #[test]
+    fn incremental_updates_match_a_refresh() {
+        fn walk(board: &Board, acc: eval::Accumulator, network: &Network, depth: usize) {
+            assert_eq!(acc, eval::Accumulator::new(board, acc.params, Some(network)), "{board}");
+
+            if depth > 0 {
+                for m in MoveGen::new_legal(board) {
+                    walk(&board.make_move_new(m), acc.make_move(board, m), network, depth - 1);
+                }
+            }
+        }
+
+        let network = random_network();
+
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+        ] {
+            let board = Board::from_str(fen).unwrap();
+            walk(&board, eval::Accumulator::new(&board, &EvalParams::default(), Some(&network)), &network, 2);
+        }
+    }
+
+    #[test]
+    fn small_network_gives_a_known_eval() {
+        // every hidden unit starts at 10, the white pawn on e2 adds 30 for white and takes 5 away
+        // for black where it's an enemy pawn on e7
+        let mut values = vec![0_i16; Network::FILE_SIZE / 2];
+        values[INPUTS * HIDDEN..INPUTS * HIDDEN + HIDDEN].fill(10);
+        values[12 * HIDDEN..13 * HIDDEN].fill(30);
+        values[(384 + 52) * HIDDEN..(384 + 53) * HIDDEN].fill(-5);
+
+        let output = INPUTS * HIDDEN + HIDDEN;
+        values[output..output + HIDDEN].fill(1);
+        values[output + HIDDEN..output + 2 * HIDDEN].fill(2);
+        values[output + 2 * HIDDEN] = 100;
+
+        let network = Network::from_bytes(&values.iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<_>>()).unwrap();
+
+        // ((40² * 256 + 5² * 2 * 256) / 255 + 100) * 400 / (255 * 64), and the other way around
+        for (fen, eval) in [("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1", 43), ("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1", 81)] {
+            let board = Board::from_str(fen).unwrap();
+            assert_eq!(network.evaluate(&Accumulator::new(&network, &board), board.side_to_move()), eval, "{fen}");
+        }
+    }
+
+    #[test]
+    fn wrong_sizes_are_rejected() {
+        assert!(Network::from_bytes(&vec![0; Network::FILE_SIZE - 2]).is_err());
+        assert!(Network::from_bytes(&vec![0; Network::FILE_SIZE + 64]).is_err());
+        assert!(Network::from_bytes(&vec![0; Network::FILE_SIZE + 16]).is_ok());
+    }
+}
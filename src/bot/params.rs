@@ -0,0 +1,474 @@
+#[cfg(feature = "uci")]
+use json::JsonValue;
+#[cfg(feature = "uci")]
+use std::io;
+use super::eval::Tapered;
+
+/// a single tunable value or a table of them, stored as json numbers and `[mid, end]` pairs
+#[cfg(feature = "uci")]
+trait Param: Sized {
+    fn to_json(&self) -> JsonValue;
+    fn from_json(value: &JsonValue) -> Option<Self>;
+
+    // flat list of weights for the tuner, `None` when a value doesn't fit
+    fn flatten(&self, out: &mut Vec<i32>);
+    fn unflatten(&mut self, values: &mut impl Iterator<Item = i32>) -> Option<()>;
+}
+
+// every weight ends up in a `Tapered` half sooner or later, so plain values are held to the same
+// range
+#[cfg(feature = "uci")]
+impl Param for i32 {
+    fn to_json(&self) -> JsonValue {
+        (*self).into()
+    }
+
+    fn from_json(value: &JsonValue) -> Option<Self> {
+        value.as_i32().filter(|v| fits(*v))
+    }
+
+    fn flatten(&self, out: &mut Vec<i32>) {
+        out.push(*self);
+    }
+
+    fn unflatten(&mut self, values: &mut impl Iterator<Item = Self>) -> Option<()> {
+        *self = values.next().filter(|v| fits(*v))?;
+        Some(())
+    }
+}
+
+#[cfg(feature = "uci")]
+impl Param for Tapered {
+    fn to_json(&self) -> JsonValue {
+        json::array![self.mid(), self.end()]
+    }
+
+    fn from_json(value: &JsonValue) -> Option<Self> {
+        let [mid, end] = <[i32; 2]>::from_json(value)?;
+        tapered(mid, end)
+    }
+
+    fn flatten(&self, out: &mut Vec<i32>) {
+        out.extend([self.mid(), self.end()]);
+    }
+
+    fn unflatten(&mut self, values: &mut impl Iterator<Item = i32>) -> Option<()> {
+        *self = tapered(values.next()?, values.next()?)?;
+        Some(())
+    }
+}
+
+#[cfg(feature = "uci")]
+impl<T: Param, const N: usize> Param for [T; N] {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Array(self.iter().map(Param::to_json).collect())
+    }
+
+    fn from_json(value: &JsonValue) -> Option<Self> {
+        if !value.is_array() {
+            return None;
+        }
+
+        value.members().map(T::from_json).collect::<Option<Vec<_>>>()?.try_into().ok()
+    }
+
+    fn flatten(&self, out: &mut Vec<i32>) {
+        self.iter().for_each(|v| v.flatten(out));
+    }
+
+    fn unflatten(&mut self, values: &mut impl Iterator<Item = i32>) -> Option<()> {
+        self.iter_mut().try_for_each(|v| v.unflatten(values))
+    }
+}
+
+// both halves are packed into 16 bits, anything bigger would wrap into garbage
+#[cfg(feature = "uci")]
+const fn fits(value: i32) -> bool {
+    value >= i16::MIN as i32 && value <= i16::MAX as i32
+}
+
+#[cfg(feature = "uci")]
+fn tapered(mid: i32, end: i32) -> Option<Tapered> {
+    (fits(mid) && fits(end)).then(|| Tapered::new(mid, end))
+}
+
+/// mid and end game tables zipped into packed pairs
+const fn pairs<const N: usize>(mid: [i32; N], end: [i32; N]) -> [Tapered; N] {
+    let mut out = [Tapered::new(0, 0); N];
+    let mut i = 0;
+
+    while i < N {
+        out[i] = Tapered::new(mid[i], end[i]);
+        i += 1;
+    }
+
+    out
+}
+
+macro_rules! eval_params {
+    ($($name: ident: $typ: ty = $default: expr,)*) => {
+        /// every weight of the classical eval, defaulting to the built in values
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub struct EvalParams {
+            $(pub $name: $typ,)*
+        }
+
+        impl Default for EvalParams {
+            fn default() -> Self {
+                Self {
+                    $($name: $default,)*
+                }
+            }
+        }
+
+        #[cfg(feature = "uci")]
+        impl EvalParams {
+            /// missing parameters keep their default so a file only needs the ones being changed
+            pub fn from_json(value: &JsonValue) -> Result<Self, String> {
+                if !value.is_object() {
+                    return Err("expected an object".to_string());
+                }
+
+                let mut params = Self::default();
+
+                for (key, value) in value.entries() {
+                    match key {
+                        $(stringify!($name) => {
+                            params.$name = Param::from_json(value).ok_or_else(|| format!("invalid value for `{key}`"))?;
+                        },)*
+                        _ => return Err(format!("unknown parameter `{key}`")),
+                    }
+                }
+
+                params.check()?;
+                Ok(params)
+            }
+
+            pub fn to_vec(&self) -> Vec<i32> {
+                let mut out = Vec::new();
+                $(self.$name.flatten(&mut out);)*
+                out
+            }
+
+            /// takes as many values as `to_vec` gives out, in the same order
+            pub fn from_slice(values: &[i32]) -> Result<Self, String> {
+                let mut params = Self::default();
+                let mut values = values.iter().copied();
+                $(params.$name.unflatten(&mut values).ok_or_else(|| format!("invalid value for `{}`", stringify!($name)))?;)*
+                params.check()?;
+                Ok(params)
+            }
+
+            /// one parameter per line so the tables stay readable
+            pub fn to_json_string(&self) -> String {
+                let lines = [$(format!("  \"{}\": {}", stringify!($name), self.$name.to_json().dump()),)*];
+                format!("{{\n{}\n}}\n", lines.join(",\n"))
+            }
+        }
+    };
+}
+
+eval_params! {
+    piece_value: [i32; 5] = PIECE_VALUE,
+    bishop_pair: Tapered = BISHOP_PAIR,
+    passed_pawn: [Tapered; 8] = pairs(PASSED_PAWN_MID, PASSED_PAWN_END),
+    free_passed_pawn: [Tapered; 8] = pairs(FREE_PASSED_PAWN_MID, FREE_PASSED_PAWN_END),
+    candidate_pawn: [Tapered; 8] = pairs(CANDIDATE_PAWN_MID, CANDIDATE_PAWN_END),
+    phalanx_pawn: [Tapered; 8] = pairs(PHALANX_PAWN_MID, PHALANX_PAWN_END),
+    supported_pawn: Tapered = SUPPORTED_PAWN,
+    doubled_pawn: Tapered = DOUBLED_PAWN,
+    isolated_pawn: Tapered = ISOLATED_PAWN,
+    backward_pawn: Tapered = BACKWARD_PAWN,
+    pawn_shield: [i32; 3] = PAWN_SHIELD,
+    pawn_storm: [i32; 5] = PAWN_STORM,
+    open_file_near_king: i32 = OPEN_FILE_NEAR_KING,
+    semi_open_file_near_king: i32 = SEMI_OPEN_FILE_NEAR_KING,
+    king_attack_weight: [i32; 6] = KING_ATTACK_WEIGHT,
+    king_safety_table: [i32; 100] = KING_SAFETY_TABLE,
+    mobility_base: [i32; 6] = MOBILITY_BASE,
+    mobility: [Tapered; 6] = pairs(MOBILITY_MID, MOBILITY_END),
+    rook_open_file: Tapered = ROOK_OPEN_FILE,
+    rook_semi_open_file: Tapered = ROOK_SEMI_OPEN_FILE,
+    rook_on_seventh: Tapered = ROOK_ON_SEVENTH,
+    knight_outpost: Tapered = KNIGHT_OUTPOST,
+    bad_bishop: Tapered = BAD_BISHOP,
+    threat_by_pawn: Tapered = THREAT_BY_PAWN,
+    threat_by_minor: Tapered = THREAT_BY_MINOR,
+    hanging_piece: Tapered = HANGING_PIECE,
+    pawn_push_threat: Tapered = PAWN_PUSH_THREAT,
+    piece_square_table: [Tapered; 64 * 6] = pairs(PIECE_SQUARE_TABLE_MID, PIECE_SQUARE_TABLE_END),
+}
+
+impl EvalParams {
+    /// sums that are only put together during the eval have to fit into a `Tapered` as well
+    #[cfg(feature = "uci")]
+    fn check(&self) -> Result<(), String> {
+        for (piece, table) in self.piece_square_table.chunks(64).enumerate() {
+            let value = self.piece_value.get(piece).copied().unwrap_or(0);
+
+            if table.iter().any(|pst| !fits(pst.mid() + value) || !fits(pst.end() + value)) {
+                return Err("`piece_value` plus `piece_square_table` is out of range".to_string());
+            }
+        }
+
+        // worst case of every file next to the king, then the whole attack table on top
+        let range = |values: &[i32]| (values.iter().copied().min().unwrap_or(0), values.iter().copied().max().unwrap_or(0));
+        let shield = range(&self.pawn_shield);
+        let storm = range(&[&self.pawn_storm[..], &[0]].concat());
+        let files = range(&[self.open_file_near_king, self.semi_open_file_near_king, 0]);
+        let attack = range(&[&self.king_safety_table[..], &[0]].concat());
+
+        let lowest = 3 * (shield.0 + storm.0 + files.0) - attack.1;
+        let highest = 3 * (shield.1 + storm.1 + files.1) - attack.0;
+
+        if !fits(lowest) || !fits(highest) {
+            return Err("king safety terms are out of range".to_string());
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "uci")]
+    pub fn load(path: &str) -> io::Result<Self> {
+        let value = json::parse(&std::fs::read_to_string(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+
+        Self::from_json(&value).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    #[cfg(feature = "uci")]
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        std::fs::write(path, self.to_json_string())
+    }
+}
+
+// the king is never traded so it's left out, its value wouldn't fit into a `Tapered` either
+pub const PIECE_VALUE: [i32; 5] = [100, 305, 333, 563, 950];
+const BISHOP_PAIR: Tapered = Tapered::new(50, 50);
+
+// pawn structure as (middle game, end game), indexed by relative rank where needed
+const PASSED_PAWN_MID: [i32; 8] = [0, 5, 5, 10, 20, 35, 55, 0];
+const PASSED_PAWN_END: [i32; 8] = [0, 10, 15, 25, 40, 65, 100, 0];
+const FREE_PASSED_PAWN_MID: [i32; 8] = [0, 0, 0, 5, 10, 15, 25, 0];
+const FREE_PASSED_PAWN_END: [i32; 8] = [0, 0, 5, 10, 20, 35, 60, 0];
+const CANDIDATE_PAWN_MID: [i32; 8] = [0, 2, 4, 6, 10, 15, 0, 0];
+const CANDIDATE_PAWN_END: [i32; 8] = [0, 5, 8, 12, 20, 30, 0, 0];
+const PHALANX_PAWN_MID: [i32; 8] = [0, 3, 5, 8, 15, 25, 40, 0];
+const PHALANX_PAWN_END: [i32; 8] = [0, 2, 4, 6, 10, 20, 30, 0];
+const SUPPORTED_PAWN: Tapered = Tapered::new(5, 8);
+const DOUBLED_PAWN: Tapered = Tapered::new(-10, -20);
+const ISOLATED_PAWN: Tapered = Tapered::new(-10, -15);
+const BACKWARD_PAWN: Tapered = Tapered::new(-8, -10);
+
+// king safety, middle game only
+const PAWN_SHIELD: [i32; 3] = [10, 5, -10]; // by distance to king, or missing
+const PAWN_STORM: [i32; 5] = [0, -5, -20, -10, -5]; // by distance to king, blocked when 1, none past 4
+const OPEN_FILE_NEAR_KING: i32 = -25;
+const SEMI_OPEN_FILE_NEAR_KING: i32 = -15;
+const KING_ATTACK_WEIGHT: [i32; 6] = [0, 2, 2, 3, 5, 0];
+
+// value from https://www.chessprogramming.org/King_Safety#Attack_Units
+const KING_SAFETY_TABLE: [i32; 100] = [
+      0,   0,   1,   2,   3,   5,   7,   9,  12,  15,
+     18,  22,  26,  30,  35,  39,  44,  50,  56,  62,
+     68,  75,  82,  85,  89,  97, 105, 113, 122, 131,
+    140, 150, 169, 180, 191, 202, 213, 225, 237, 248,
+    260, 272, 283, 295, 307, 319, 330, 342, 354, 366,
+    377, 389, 401, 412, 424, 436, 448, 459, 471, 483,
+    494, 500, 500, 500, 500, 500, 500, 500, 500, 500,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500,
+];
+
+// piece activity as (middle game, end game), mobility is per safe square past the base count
+const MOBILITY_BASE: [i32; 6] = [0, 4, 6, 7, 13, 0];
+const MOBILITY_MID: [i32; 6] = [0, 4, 5, 2, 1, 0];
+const MOBILITY_END: [i32; 6] = [0, 4, 5, 4, 2, 0];
+const ROOK_OPEN_FILE: Tapered = Tapered::new(25, 10);
+const ROOK_SEMI_OPEN_FILE: Tapered = Tapered::new(12, 6);
+const ROOK_ON_SEVENTH: Tapered = Tapered::new(20, 30);
+const KNIGHT_OUTPOST: Tapered = Tapered::new(20, 12);
+const BAD_BISHOP: Tapered = Tapered::new(-3, -5); // per own pawn on the bishop's colour
+
+// threats as (middle game, end game), per attacked piece
+const THREAT_BY_PAWN: Tapered = Tapered::new(120, 80);
+const THREAT_BY_MINOR: Tapered = Tapered::new(60, 50); // on rooks and queens
+const HANGING_PIECE: Tapered = Tapered::new(60, 30);
+const PAWN_PUSH_THREAT: Tapered = Tapered::new(20, 15);
+
+// a1 ----> h1
+// |
+// v
+// a8
+//
+// value mostly from https://www.chessprogramming.org/Simplified_Evaluation_Function
+// pawn endgame was added by me
+const PIECE_SQUARE_TABLE_MID: [i32; 64 * 6] = [
+    // Pawn
+      0,   0,   0,   0,   0,   0,   0,   0,
+      5,  10,  10, -20, -20,  10,  10,   5,
+      5,  -5, -10,   0,   0, -10,  -5,   5,
+      0,   0,   0,  20,  20,   0,   0,   0,
+      5,   5,  10,  25,  25,  10,   5,   5,
+     10,  10,  20,  30,  30,  20,  10,  10,
+     50,  50,  50,  50,  50,  50,  50,  50,
+      0,   0,   0,   0,   0,   0,   0,   0,
+    // Knight
+    -50, -40, -30, -30, -30, -30, -40, -50,
+    -40, -20,   0,   5,   5,   0, -20, -40,
+    -30,   5,  10,  15,  15,  10,   5, -30,
+    -30,   0,  15,  20,  20,  15,   0, -30,
+    -30,   5,  15,  20,  20,  15,   5, -30,
+    -30,   0,  10,  15,  15,  10,   0, -30,
+    -40, -20,   0,   0,   0,   0, -20, -40,
+    -50, -40, -30, -30, -30, -30, -40, -50,
+    // Bishop
+    -20, -10, -10, -10, -10, -10, -10, -20,
+    -10,   5,   0,   0,   0,   0,   5, -10,
+    -10,  10,  10,  10,  10,  10,  10, -10,
+    -10,   0,  10,  10,  10,  10,   0, -10,
+    -10,   5,   5,  10,  10,   5,   5, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -20, -10, -10, -10, -10, -10, -10, -20,
+    // Rook
+      0,   0,   0,   5,   5,   0,   0,   0,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+      5,  10,  10,  10,  10,  10,  10,   5,
+      0,   0,   0,   0,   0,   0,   0,   0,
+    // Queen
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+    -10,   0,   5,   0,   0,   0,   0, -10,
+    -10,   5,   5,   5,   5,   5,   0, -10,
+      0,   0,   5,   5,   5,   5,   0,  -5,
+     -5,   0,   5,   5,   5,   5,   0,  -5,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+    // King
+     20,  30,  10,   0,   0,  10,  30,  20,
+     20,  20,   0,   0,   0,   0,  20,  20,
+    -10, -20, -20, -20, -20, -20, -20, -10,
+    -20, -30, -30, -40, -40, -30, -30, -20,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+];
+
+const PIECE_SQUARE_TABLE_END: [i32; 64 * 6] = [
+    // Pawn
+      0,   0,   0,   0,   0,   0,   0,   0,
+    -20, -20, -20, -20, -20, -20, -20, -20,
+    -10, -10, -10, -10, -10, -10, -10, -10,
+     20,  20,  20,  20,  20,  20,  20,  20,
+     30,  30,  30,  30,  30,  30,  30,  30,
+     40,  40,  40,  40,  40,  40,  40,  40,
+     50,  50,  50,  50,  50,  50,  50,  50,
+      0,   0,   0,   0,   0,   0,   0,   0,
+    // Knight
+    -50, -40, -30, -30, -30, -30, -40, -50,
+    -40, -20,   0,   5,   5,   0, -20, -40,
+    -30,   5,  10,  15,  15,  10,   5, -30,
+    -30,   0,  15,  20,  20,  15,   0, -30,
+    -30,   5,  15,  20,  20,  15,   5, -30,
+    -30,   0,  10,  15,  15,  10,   0, -30,
+    -40, -20,   0,   0,   0,   0, -20, -40,
+    -50, -40, -30, -30, -30, -30, -40, -50,
+    // Bishop
+    -20, -10, -10, -10, -10, -10, -10, -20,
+    -10,   5,   0,   0,   0,   0,   5, -10,
+    -10,  10,  10,  10,  10,  10,  10, -10,
+    -10,   0,  10,  10,  10,  10,   0, -10,
+    -10,   5,   5,  10,  10,   5,   5, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -20, -10, -10, -10, -10, -10, -10, -20,
+    // Rook
+      0,   0,   0,   5,   5,   0,   0,   0,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+      5,  10,  10,  10,  10,  10,  10,   5,
+      0,   0,   0,   0,   0,   0,   0,   0,
+    // Queen
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+    -10,   0,   5,   0,   0,   0,   0, -10,
+    -10,   5,   5,   5,   5,   5,   0, -10,
+      0,   0,   5,   5,   5,   5,   0,  -5,
+     -5,   0,   5,   5,   5,   5,   0,  -5,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+    // King
+    -50, -30, -30, -30, -30, -30, -30, -50,
+    -30, -30,   0,   0,   0,   0, -30, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -20, -10,   0,   0, -10, -20, -30,
+    -50, -40, -30, -20, -20, -30, -40, -50,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_json() {
+        let mut params = EvalParams { bishop_pair: Tapered::new(40, 60), ..Default::default() };
+        params.piece_square_table[100] = Tapered::new(12, -7);
+
+        let parsed = json::parse(&params.to_json_string()).unwrap();
+        assert_eq!(EvalParams::from_json(&parsed), Ok(params.clone()));
+        assert_eq!(EvalParams::from_slice(&params.to_vec()), Ok(params));
+    }
+
+    #[test]
+    fn tapered_values_must_fit() {
+        let parsed = json::parse(r#"{ "bishop_pair": [50, 40000] }"#).unwrap();
+        assert_eq!(EvalParams::from_json(&parsed), Err("invalid value for `bishop_pair`".to_string()));
+
+        let mut values = EvalParams::default().to_vec();
+        // right after the piece values
+        values[5] = -32769;
+        assert_eq!(EvalParams::from_slice(&values), Err("invalid value for `bishop_pair`".to_string()));
+
+        // plain values end up in a `Tapered` too
+        values[5] = 50;
+        values[0] = 100_000;
+        assert_eq!(EvalParams::from_slice(&values), Err("invalid value for `piece_value`".to_string()));
+
+        // each half fits on its own but not once they're added up
+        let parsed = json::parse(r#"{ "piece_value": [100, 305, 333, 563, 32767] }"#).unwrap();
+        assert_eq!(
+            EvalParams::from_json(&parsed),
+            Err("`piece_value` plus `piece_square_table` is out of range".to_string())
+        );
+
+        let parsed = json::parse(r#"{ "open_file_near_king": -12000 }"#).unwrap();
+        assert_eq!(EvalParams::from_json(&parsed), Err("king safety terms are out of range".to_string()));
+    }
+
+    #[test]
+    fn partial_files_keep_defaults() {
+        let parsed = json::parse(r#"{ "piece_value": [90, 300, 320, 500, 900], "doubled_pawn": [-5, -25] }"#).unwrap();
+        let params = EvalParams::from_json(&parsed).unwrap();
+
+        assert_eq!(params.piece_value, [90, 300, 320, 500, 900]);
+        assert_eq!(params.doubled_pawn, Tapered::new(-5, -25));
+        assert_eq!(params.king_safety_table, EvalParams::default().king_safety_table);
+
+        for bad in [r#"{ "piece_value": [1, 2] }"#, r#"{ "no_such_weight": 1 }"#, r#"{ "doubled_pawn": 3 }"#] {
+            assert!(EvalParams::from_json(&json::parse(bad).unwrap()).is_err(), "{bad}");
+        }
+    }
+}
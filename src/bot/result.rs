@@ -0,0 +1,92 @@
+use chess::*;
+use core::fmt;
+use std::time::Duration;
+use super::eval::*;
+use super::trans_table::TransTableStats;
+use super::cache::CacheStats;
+
+#[derive(Debug, Clone)]
+#[cfg_attr(not(feature = "uci"), allow(dead_code))]
+pub struct SearchResult {
+    pub best_move: ChessMove,
+    pub ponder_move: Option<ChessMove>,
+    pub score: Score,
+    pub bound: Bound,
+
+    pub depth: usize,
+    pub seldepth: usize,
+    pub nodes: usize,
+    pub elapsed: Duration,
+
+    pub pv: Vec<ChessMove>,
+
+    pub hashfull: usize,
+    pub tt_stats: Option<TransTableStats>,
+    pub eval_cache_stats: Option<CacheStats>,
+    pub pawn_table_stats: Option<CacheStats>,
+}
+
+/// evaluation from the side to move's perspective
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Score {
+    /// centipawns
+    Cp(i32),
+    /// full moves until mate, negative if the side to move is getting mated
+    Mate(i32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+impl Score {
+    pub const fn from_eval(eval: i32) -> Self {
+        if eval >= MATE_THRESHOLD {
+            Self::Mate((MAX_EVAL - eval + 1) / 2)
+        } else if eval <= -MATE_THRESHOLD {
+            Self::Mate(-(eval - MIN_EVAL) / 2)
+        } else {
+            Self::Cp(eval)
+        }
+    }
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Cp(cp) => write!(f, "cp {cp}"),
+            Self::Mate(n) => write!(f, "mate {n}"),
+        }
+    }
+}
+
+impl SearchResult {
+    /// straight from the opening book, nothing got searched
+    pub fn from_book(best_move: ChessMove, elapsed: Duration) -> Self {
+        Self {
+            best_move,
+            ponder_move: None,
+            score: Score::Cp(0),
+            bound: Bound::Exact,
+
+            depth: 0,
+            seldepth: 0,
+            nodes: 0,
+            elapsed,
+
+            pv: vec![best_move],
+
+            hashfull: 0,
+            tt_stats: None,
+            eval_cache_stats: None,
+            pawn_table_stats: None,
+        }
+    }
+
+    pub fn pv_string(&self) -> String {
+        self.pv.iter().map(|m| m.to_string()).collect::<Vec<_>>().join(" ")
+    }
+}
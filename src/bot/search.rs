@@ -1,12 +1,12 @@
 use chess::*;
 use super::config::*;
+use super::endgame::is_dead_draw;
 use super::eval::*;
+use super::result::*;
+use super::trans_table::TransTableEntry;
 use rayon::prelude::*;
 use std::sync::atomic::*;
 
-const NON_ZERO_WINDOW: usize = 1 << 31;
-const PV_NODE: usize = 1 << 30;
-
 macro_rules! eq {
     ($a: expr, $b: expr) => { $a == $b };
     ($a: expr, $b: expr, $($rest: tt)+) => {
@@ -15,30 +15,46 @@ macro_rules! eq {
 }
 
 impl super::Game {
-    pub fn search(&mut self) -> (ChessMove, i32) {
+    pub fn search(&self) -> SearchResult {
+        self.searched.store(0, Ordering::Relaxed);
+        self.seldepth.store(0, Ordering::Relaxed);
+        self.trans_table.reset_stats();
+        self.eval_cache.reset_stats();
+        self.pawn_table.reset_stats();
+        self.pool.install(|| self.iterative_deepening())
+    }
+
+    fn iterative_deepening(&self) -> SearchResult {
         let gen = MoveGen::new_legal(&self.board);
         let mut moves = Vec::with_capacity(gen.len());
+        let root_acc = Accumulator::new(&self.board, &self.params, self.network.as_deref());
 
         for m in gen {
             let board = self.board.make_move_new(m);
-            let eval = super::eval::evaluate(&board);
+            let eval = self.cached_eval(&board).unwrap_or_else(|| self.evaluate_uncached(&board, &root_acc.make_move(&self.board, m)));
             moves.push((m, eval));
         }
 
         // reducing move time if there is a single move left
         if moves.len() == 1 {
-            dbg!("only move is {}", moves[0].0);
-            return moves[0];
+            info!("only move is {}", moves[0].0);
+            return self.make_result(&moves, 0, Bound::Exact);
         }
 
         moves.sort_by_key(|a| -a.1);
 
-        for i in 1..=MAX_SEARCH_DEPTH {
+        let mut last_nodes = 0;
+        let mut completed = 0;
+        let mut bound = Bound::Exact;
+
+        for i in 1..=self.limits.depth.min(MAX_SEARCH_DEPTH) {
             let max_eval = AtomicI32::new(MIN_EVAL);
+            let aborted = AtomicBool::new(false);
             let start = std::time::Instant::now();
 
-            moves.par_iter_mut().enumerate().for_each(|(j, (m, e))| {
+            let search_root = |(j, (m, e)): (usize, &mut (ChessMove, i32))| {
                 let board = self.board.make_move_new(*m);
+                let acc = root_acc.make_move(&self.board, *m);
 
                 let mut depth = i;
                 depth -= (j >= REDUCED_SEARCH_DEPTH) as usize;
@@ -48,6 +64,7 @@ impl super::Game {
 
                 let mut eval = -self.alpha_beta_search(
                     board,
+                    &acc,
                     &mut moves,
                     depth,
                     SEARCH_EXTENSION_LIMIT,
@@ -59,12 +76,14 @@ impl super::Game {
                 );
 
                 if self.times_up() {
+                    aborted.store(true, Ordering::Relaxed);
                     return;
                 }
 
                 if j >= REDUCED_SEARCH_DEPTH && eval > max_eval.load(Ordering::Relaxed) {
                     let new_eval = -self.alpha_beta_search(
                         board,
+                        &acc,
                         &mut moves,
                         depth + 1,
                         SEARCH_EXTENSION_LIMIT,
@@ -84,15 +103,42 @@ impl super::Game {
                 }
 
                 *e = eval;
-            });
+            };
+
+            if self.is_deterministic() {
+                moves.iter_mut().enumerate().for_each(search_root);
+            } else {
+                moves.par_iter_mut().enumerate().for_each(search_root);
+            }
 
             moves.sort_by_key(|a| -a.1);
 
             let elapsed = start.elapsed().as_secs_f32();
-            let nodes = self.searched.swap(0, Ordering::Relaxed);
-            info!("depth {} searched in {:.2}s (nodes: {}, MN/s: {:.2})", i, elapsed, nodes, (nodes as f32 / 1_000_000.0) / elapsed);
+            let total_nodes = self.searched.load(Ordering::Relaxed);
+            let nodes = total_nodes - last_nodes;
+            last_nodes = total_nodes;
+            info!(
+                "depth {} searched in {:.2}s (nodes: {}, MN/s: {:.2}, hashfull: {}‰)",
+                i, elapsed, nodes, (nodes as f32 / 1_000_000.0) / elapsed, self.trans_table.hashfull(self.age),
+            );
+
+            if let Some(stats) = self.trans_table.stats() {
+                info!("transposition table: {}", stats);
+            }
 
-            if moves.iter().any(|a| a.1 == MAX_EVAL) {
+            if let (Some(eval_cache), Some(pawn_table)) = (self.eval_cache.stats(), self.pawn_table.stats()) {
+                info!("eval cache: {}, pawn table: {}", eval_cache, pawn_table);
+            }
+
+            // not every root move got searched at this depth, so the best one is only a lower bound
+            if aborted.load(Ordering::Relaxed) {
+                bound = Bound::Lower;
+                break;
+            }
+
+            completed = i;
+
+            if moves.iter().any(|a| a.1 >= MATE_THRESHOLD) {
                 info!("found checkmate");
                 break;
             } else if self.times_up() {
@@ -102,16 +148,57 @@ impl super::Game {
 
         moves.sort_by_key(|a| -a.1);
 
-        for m in moves.iter() {
-            dbg!("{} {}", m.0, m.1);
+        self.make_result(&moves, completed, bound)
+    }
+
+    fn make_result(&self, moves: &[(ChessMove, i32)], depth: usize, bound: Bound) -> SearchResult {
+        let (best_move, eval) = moves[0];
+        let pv = self.principal_variation(best_move, depth);
+
+        SearchResult {
+            best_move,
+            ponder_move: pv.get(1).copied(),
+            score: Score::from_eval(eval),
+            bound,
+
+            depth,
+            seldepth: self.seldepth.load(Ordering::Relaxed).max(depth),
+            nodes: self.searched.load(Ordering::Relaxed),
+            elapsed: self.time_ref.elapsed(),
+
+            pv,
+
+            hashfull: self.trans_table.hashfull(self.age),
+            tt_stats: self.trans_table.stats(),
+            eval_cache_stats: self.eval_cache.stats(),
+            pawn_table_stats: self.pawn_table.stats(),
+        }
+    }
+
+    /// follows the best moves stored in the transposition table
+    fn principal_variation(&self, best_move: ChessMove, depth: usize) -> Vec<ChessMove> {
+        let mut pv = vec![best_move];
+        let mut board = self.board.make_move_new(best_move);
+
+        while pv.len() < depth.max(1) {
+            let Some(m) = self.trans_table.get(board.get_hash()).and_then(|t_e| t_e.next) else { break };
+
+            if !board.legal(m) {
+                break;
+            }
+
+            pv.push(m);
+            board = board.make_move_new(m);
         }
 
-        *moves.first().unwrap()
+        pv
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn alpha_beta_search(
         &self,
         current: Board,
+        acc: &Accumulator,
         moves: &mut Vec<ChessMove>, // reuse the same vec to avoid alloc
         depth: usize,
         ext_depth: usize,
@@ -122,17 +209,30 @@ impl super::Game {
     ) -> i32 {
         self.searched.fetch_add(1, Ordering::Relaxed);
 
+        let ply = moves.len() - self.moves.len();
+        if ply > self.seldepth.load(Ordering::Relaxed) {
+            self.seldepth.fetch_max(ply, Ordering::Relaxed);
+        }
+
         if matches!(current.status(), BoardStatus::Checkmate) {
-            return MIN_EVAL;
-        } else if matches!(current.status(), BoardStatus::Stalemate) {
-            return 0;
+            return MIN_EVAL + ply as i32;
+        } else if matches!(current.status(), BoardStatus::Stalemate) || is_dead_draw(&current) {
+            return self.draw_eval(&current);
         }
 
-        let tt_depth = depth + NON_ZERO_WINDOW * !zero_window as usize;
+        let mut tt_move = None;
 
         if let Some(t_e) = self.trans_table.get(current.get_hash()) {
-            if (!is_pv || (alpha < t_e.eval && t_e.eval < beta)) && t_e.depth >= tt_depth {
-                return t_e.eval;
+            let eval = eval_from_tt(t_e.eval, ply);
+            tt_move = t_e.next;
+
+            // pv nodes only take exact evals so the line stays intact
+            if t_e.depth >= depth && (!is_pv || t_e.bound == Bound::Exact) && match t_e.bound {
+                Bound::Exact => true,
+                Bound::Lower => eval >= beta,
+                Bound::Upper => eval <= alpha,
+            } {
+                return eval;
             }
         }
 
@@ -141,13 +241,14 @@ impl super::Game {
         }
 
         if depth == 0 {
-            return Self::quiescene_search(current, alpha, beta);
+            return self.quiescene_search(current, acc, ply, alpha, beta);
         }
 
         // null move pruning
         if let Some(board) = current.null_move() {
             let eval = -self.zero_window_search(
                 board,
+                acc,
                 moves,
                 depth.saturating_sub(4),
                 ext_depth,
@@ -163,10 +264,12 @@ impl super::Game {
             }
         }
 
+        let alpha_orig = alpha;
         let mut max_eval = if zero_window { alpha } else { MIN_EVAL };
         let mut alpha_raised = false;
+        let mut best_move = None;
 
-        for (i, m) in self.move_in_order(&current).into_iter().enumerate() {
+        for (i, m) in self.move_in_order(&current, acc, tt_move).into_iter().enumerate() {
             let mc = moves.len();
             let eval = if mc < 7 || !(
                 eq!(moves.get(mc - 7), moves.get(mc - 3)) && // chain of 3 fold
@@ -175,6 +278,7 @@ impl super::Game {
                 eq!(moves.get(mc - 4), Some(&m))
             ) {
                 let after = current.make_move_new(m);
+                let after_acc = acc.make_move(&current, m);
                 let mut ext = 0;
                 ext += (after.checkers().0 != 0) as usize;
                 ext += m.get_promotion().is_some() as usize;
@@ -186,6 +290,7 @@ impl super::Game {
                     if !alpha_raised {
                         -self.alpha_beta_search(
                             after,
+                            &after_acc,
                             moves,
                             depth.max(0) as usize,
                             ext_depth - ext,
@@ -197,6 +302,7 @@ impl super::Game {
                     } else {
                         let eval = -self.zero_window_search(
                             after,
+                            &after_acc,
                             moves,
                             depth.max(0) as usize,
                             ext_depth - ext,
@@ -206,6 +312,7 @@ impl super::Game {
                         if max_eval < eval && eval < beta {
                             -self.alpha_beta_search(
                                 after,
+                                &after_acc,
                                 moves,
                                 depth.max(0) as usize,
                                 ext_depth - ext,
@@ -221,6 +328,7 @@ impl super::Game {
                 } else {
                     -self.zero_window_search(
                         after,
+                        &after_acc,
                         moves,
                         depth.max(0) as usize,
                         ext_depth - ext,
@@ -252,23 +360,17 @@ impl super::Game {
                 //     eval += PIECE_VALUE[current.piece_on(m.get_dest()).unwrap().to_index()] / 100;
                 // }
 
-                self.trans_table.insert(current.get_hash(),
-                    super::trans_table::TransTableEntry {
-                        depth: tt_depth,
-                        eval,
-                        age: self.age,
-                    }
-                );
-
                 eval
             } else {
-                0
+                self.draw_eval(&current)
             };
 
             if eval >= beta {
+                self.store(&current, depth, ply, eval, Some(m), Bound::Lower);
                 return eval;
             } else if !zero_window && eval > max_eval {
                 max_eval = eval;
+                best_move = Some(m);
 
                 if eval > alpha {
                     alpha = eval;
@@ -277,21 +379,43 @@ impl super::Game {
             }
         }
 
+        let bound = if max_eval > alpha_orig { Bound::Exact } else { Bound::Upper };
+        self.store(&current, depth, ply, max_eval, best_move.or(tt_move), bound);
+
         max_eval
     }
 
+    /// a draw from `board`'s side to move's perspective, worse for the root side with contempt
+    #[inline(always)]
+    fn draw_eval(&self, board: &Board) -> i32 {
+        if board.side_to_move() == self.board.side_to_move() { -self.contempt } else { self.contempt }
+    }
+
+    fn store(&self, board: &Board, depth: usize, ply: usize, eval: i32, next: Option<ChessMove>, bound: Bound) {
+        self.trans_table.insert(board.get_hash(), TransTableEntry {
+            depth,
+            eval: eval_to_tt(eval, ply),
+            age: self.age,
+            next,
+            bound,
+        });
+    }
+
     pub fn quiescene_search(
+        &self,
         current: Board,
+        acc: &Accumulator,
+        ply: usize,
         mut alpha: i32,
         beta: i32,
     ) -> i32 {
         if matches!(current.status(), BoardStatus::Checkmate) {
-            return MIN_EVAL;
-        } else if matches!(current.status(), BoardStatus::Stalemate) {
-            return 0;
+            return MIN_EVAL + ply as i32;
+        } else if matches!(current.status(), BoardStatus::Stalemate) || is_dead_draw(&current) {
+            return self.draw_eval(&current);
         }
 
-        let eval = evaluate(&current);
+        let eval = self.evaluate(&current, acc);
         let mut max_eval = eval;
 
         if eval >= beta {
@@ -306,7 +430,7 @@ impl super::Game {
 
         for m in movegen {
             let board = current.make_move_new(m);
-            let eval = -Self::quiescene_search(board, -beta, -alpha);
+            let eval = -self.quiescene_search(board, &acc.make_move(&current, m), ply + 1, -beta, -alpha);
 
             if eval >= beta {
                 return eval;
@@ -322,29 +446,165 @@ impl super::Game {
         max_eval
     }
 
+    /// follows the captures `quiescene_search` would play until the position is quiet
+    #[cfg(feature = "uci")]
+    pub fn quiet_position(&self, board: Board) -> Board {
+        let acc = Accumulator::new(&board, &self.params, self.network.as_deref());
+        let mut max_eval = self.evaluate(&board, &acc);
+        let mut best_move = None;
+
+        let mut movegen = MoveGen::new_legal(&board);
+        movegen.set_iterator_mask(*board.color_combined(!board.side_to_move()));
+
+        for m in movegen {
+            let eval = -self.quiescene_search(board.make_move_new(m), &acc.make_move(&board, m), 1, MIN_EVAL, MAX_EVAL);
+
+            if eval > max_eval {
+                max_eval = eval;
+                best_move = Some(m);
+            }
+        }
+
+        best_move.map_or(board, |m| self.quiet_position(board.make_move_new(m)))
+    }
+
     fn zero_window_search(
         &self,
         current: Board,
+        acc: &Accumulator,
         moves: &mut Vec<ChessMove>,
         depth: usize,
         ext_depth: usize,
         beta: i32
     ) -> i32 {
-        self.alpha_beta_search(current, moves, depth, ext_depth, beta - 1, beta, false, true)
+        self.alpha_beta_search(current, acc, moves, depth, ext_depth, beta - 1, beta, false, true)
     }
 
-    fn move_in_order(&self, board: &Board) -> Vec<ChessMove> {
+    fn move_in_order(&self, board: &Board, acc: &Accumulator, tt_move: Option<ChessMove>) -> Vec<ChessMove> {
         let gen = MoveGen::new_legal(board);
         let mut buf = Vec::with_capacity(gen.len());
 
         buf.extend(gen);
 
         buf.sort_by_cached_key(|a| {
-            let a = board.make_move_new(*a);
+            if Some(*a) == tt_move {
+                return MIN_EVAL;
+            }
 
-            self.trans_table.get(a.get_hash()).map_or_else(|| evaluate(&a), |a| a.eval)
+            let after = board.make_move_new(*a);
+
+            // the child accumulator is only built when neither table knows the position
+            self.trans_table
+                .get(after.get_hash())
+                .map(|t_e| t_e.eval)
+                .or_else(|| self.cached_eval(&after))
+                .unwrap_or_else(|| self.evaluate_uncached(&after, &acc.make_move(board, *a)))
         });
 
         buf
     }
 }
+
+/// mate scores are stored relative to the node so they stay correct when reached from another ply
+const fn eval_to_tt(eval: i32, ply: usize) -> i32 {
+    if eval >= MATE_THRESHOLD {
+        eval + ply as i32
+    } else if eval <= -MATE_THRESHOLD {
+        eval - ply as i32
+    } else {
+        eval
+    }
+}
+
+const fn eval_from_tt(eval: i32, ply: usize) -> i32 {
+    if eval >= MATE_THRESHOLD {
+        eval - ply as i32
+    } else if eval <= -MATE_THRESHOLD {
+        eval + ply as i32
+    } else {
+        eval
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Game, SearchLimits, TimeControl};
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn contempt_is_relative_to_the_root_side() {
+        // every move keeps it a dead draw
+        for fen in [
+            "8/8/4k3/8/8/3K4/5N2/8 w - - 0 1",
+            "8/8/4k3/8/8/3K4/5N2/8 b - - 0 1",
+            "8/3k4/8/3K4/3P4/8/8/8 w - - 0 1",
+        ] {
+            let mut game = Game::with_hash_size(Board::from_str(fen).unwrap(), Vec::new(), 1);
+            game.set_threads(1);
+            game.contempt = 30;
+            game.time_ctrl = TimeControl { time_left: usize::MAX, time_incr: 0 };
+            game.limits = SearchLimits { depth: 3, ..SearchLimits::default() };
+
+            assert_eq!(game.play().score, Score::Cp(-30), "{fen}");
+        }
+    }
+
+    #[test]
+    fn single_thread_search_is_deterministic() {
+        let search = || {
+            let board = Board::from_str("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4").unwrap();
+            let mut game = Game::with_hash_size(board, Vec::new(), 1);
+            game.set_threads(1);
+            game.time_ctrl = TimeControl { time_left: usize::MAX, time_incr: 0 };
+            game.limits = SearchLimits { depth: 3, ..SearchLimits::default() };
+
+            let result = game.play();
+            (result.best_move, result.nodes, result.score)
+        };
+
+        assert_eq!(search(), search());
+    }
+
+    #[test]
+    fn draw_scores_are_cleared_when_the_root_side_changes() {
+        let mut game = Game::with_hash_size(Board::from_str("8/8/4k3/8/8/3K4/5N2/8 w - - 0 1").unwrap(), Vec::new(), 1);
+        game.set_threads(1);
+        game.contempt = 30;
+        game.time_ctrl = TimeControl { time_left: usize::MAX, time_incr: 0 };
+        game.limits = SearchLimits { depth: 2, ..SearchLimits::default() };
+
+        // after its move it's black's turn at the root, so white's draw scores have to go
+        game.play();
+        game.trans_table.insert(42, TransTableEntry { depth: 1, eval: -30, age: game.age, next: None, bound: Bound::Exact });
+        game.play();
+        assert!(game.trans_table.get(42).is_none());
+    }
+
+    #[test]
+    fn quiescence_mates_count_the_ply() {
+        let game = Game::with_hash_size(Board::default(), Vec::new(), 1);
+        let eval = |fen: &str, ply| {
+            let board = Board::from_str(fen).unwrap();
+            game.quiescene_search(board, &Accumulator::new(&board, &game.params, None), ply, MIN_EVAL, MAX_EVAL)
+        };
+
+        assert_eq!(eval("4R2k/6pp/8/8/8/8/8/K7 b - - 0 1", 3), MIN_EVAL + 3);
+
+        // the back rank mate is a capture, so only quiescence sees it
+        assert_eq!(eval("4n2k/6pp/8/8/8/8/8/K3R3 w - - 0 1", 2), MAX_EVAL - 3);
+        assert_eq!(Score::from_eval(MAX_EVAL - 3), Score::Mate(2));
+    }
+
+    #[test]
+    fn quiescence_scores_dead_draws() {
+        let mut game = Game::with_hash_size(Board::default(), Vec::new(), 1);
+        game.contempt = 30;
+
+        // after Kxd4 a lone bishop can't win, so it's a draw that white at the root doesn't want
+        let dead = Board::from_str("8/8/4k3/8/3K4/8/5B2/8 b - - 0 1").unwrap();
+        let acc = Accumulator::new(&dead, &game.params, None);
+
+        assert_eq!(game.quiescene_search(dead, &acc, 1, MIN_EVAL, MAX_EVAL), 30);
+    }
+}
@@ -1,95 +1,617 @@
 use core::mem::*;
 use std::sync::atomic::*;
-use super::config::T_TABLE_SIZE;
+#[cfg(feature = "uci")]
+use std::io::{self, Read, Write};
+#[cfg(feature = "uci")]
+use std::path::Path;
+#[cfg(feature = "uci")]
+use std::str::FromStr;
+use chess::*;
+use rayon::prelude::*;
+use super::config::T_TABLE_CLUSTER_SIZE;
+use super::result::Bound;
 
-const MASK: usize = T_TABLE_SIZE - 1;
+// how many plies of depth a search older is worth when picking an entry to replace
+const AGE_WEIGHT: isize = 4;
+
+#[cfg(feature = "uci")]
+const FILE_MAGIC: &[u8; 8] = b"CBOTTT\0\0";
+#[cfg(feature = "uci")]
+const FILE_VERSION: u32 = 1;
+// bump whenever `TransTableEntry::pack` changes
+#[cfg(feature = "uci")]
+const ENTRY_LAYOUT: u32 = 2;
+
+const AGE_MASK: usize = 0x3f;
 
 pub struct TransTableEntry {
     pub depth: usize,
     pub eval: i32,
     pub age: usize,
+    pub next: Option<ChessMove>,
+    pub bound: Bound,
 }
 
 impl TransTableEntry {
+    // eval: 32 bits | move: 16 bits | depth: 8 bits | age: 6 bits | bound: 2 bits
+    #[inline(always)]
+    fn pack(&self) -> u64 {
+        (self.eval as u32 as u64)
+            | ((pack_move(self.next) as u64) << 32)
+            | ((self.depth.min(0xff) as u64) << 48)
+            | (((self.age & AGE_MASK) as u64) << 56)
+            | ((self.bound as u64) << 62)
+    }
+
     #[inline(always)]
-    fn to_u8s<'a>(&'a self) -> &'a [u8] {
-        unsafe { transmute::<_, &'a [u8; size_of::<Self>()]>(self) }
+    fn unpack(data: u64) -> Self {
+        Self {
+            eval: data as i32,
+            next: unpack_move((data >> 32) as u16),
+            depth: (data >> 48) as usize & 0xff,
+            age: (data >> 56) as usize & AGE_MASK,
+            bound: match data >> 62 {
+                0 => Bound::Exact,
+                1 => Bound::Lower,
+                _ => Bound::Upper,
+            },
+        }
     }
 }
 
+// 0 is a1a1 which is never legal so it's used for no move
+#[inline(always)]
+fn pack_move(m: Option<ChessMove>) -> u16 {
+    m.map_or(0, |m| {
+        let promo = m.get_promotion().map_or(0, |p| p.to_index() as u16);
+        m.get_source().to_index() as u16 | ((m.get_dest().to_index() as u16) << 6) | (promo << 12)
+    })
+}
+
+#[inline(always)]
+fn unpack_move(m: u16) -> Option<ChessMove> {
+    if m == 0 {
+        return None;
+    }
+
+    let src = unsafe { Square::new(m as u8 & 63) };
+    let dst = unsafe { Square::new((m >> 6) as u8 & 63) };
+    let promo = match m >> 12 {
+        1 => Some(Piece::Knight),
+        2 => Some(Piece::Bishop),
+        3 => Some(Piece::Rook),
+        4 => Some(Piece::Queen),
+        _ => None,
+    };
+
+    Some(ChessMove::new(src, dst, promo))
+}
+
+#[cfg(feature = "uci")]
+fn read_u32(r: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    r.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+#[cfg(feature = "uci")]
+fn read_u64(r: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+#[cfg(feature = "uci")]
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// changes whenever the `chess` crate changes its zobrist keys, which would make saved entries
+/// belong to different positions
+#[cfg(feature = "uci")]
+fn zobrist_fingerprint() -> u64 {
+    [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        "8/2k5/8/8/8/8/5K2/8 b - - 0 1",
+    ]
+        .iter()
+        .fold(0, |acc, fen| acc.rotate_left(17) ^ Board::from_str(fen).unwrap().get_hash())
+}
+
+/// `hash` is stored xored with `data`, so an entry torn by another thread writing at the same
+/// time no longer matches its key and just reads as a miss
+#[derive(Default)]
 pub struct HashTableEntry {
     pub hash: AtomicU64,
-    pub age_depth: AtomicU64,
-    pub eval_checksum: AtomicU64,
+    pub data: AtomicU64,
+}
+
+impl HashTableEntry {
+    #[inline(always)]
+    pub fn load(&self) -> (u64, u64) {
+        let data = self.data.load(Ordering::Relaxed);
+        (self.hash.load(Ordering::Relaxed) ^ data, data)
+    }
+
+    #[inline(always)]
+    pub fn store(&self, k: u64, data: u64) {
+        self.hash.store(k ^ data, Ordering::Relaxed);
+        self.data.store(data, Ordering::Relaxed);
+    }
 }
 
 impl Clone for HashTableEntry {
     fn clone(&self) -> Self {
         Self {
             hash: AtomicU64::new(self.hash.load(Ordering::Relaxed)),
-            age_depth: AtomicU64::new(self.age_depth.load(Ordering::Relaxed)),
-            eval_checksum: AtomicU64::new(self.eval_checksum.load(Ordering::Relaxed)),
+            data: AtomicU64::new(self.data.load(Ordering::Relaxed)),
         }
     }
 }
 
 pub struct TransTable {
-    inner: Box<[HashTableEntry; T_TABLE_SIZE]>,
+    inner: Box<[HashTableEntry]>,
+
+    #[cfg(feature = "stats")]
+    counters: Counters,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TransTableStats {
+    pub probes: usize,
+    pub hits: usize,
+    pub stores: usize,
+    /// stores that evicted an entry of another position
+    pub collisions: usize,
+}
+
+impl core::fmt::Display for TransTableStats {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(
+            f,
+            "probes {} hits {} ({:.1}%) stores {} collisions {}",
+            self.probes,
+            self.hits,
+            self.hits as f32 * 100.0 / self.probes.max(1) as f32,
+            self.stores,
+            self.collisions,
+        )
+    }
+}
+
+#[cfg(feature = "stats")]
+#[derive(Default)]
+struct Counters {
+    probes: AtomicUsize,
+    hits: AtomicUsize,
+    stores: AtomicUsize,
+    collisions: AtomicUsize,
+}
+
+macro_rules! count {
+    ($self: ident . $counter: ident) => {
+        #[cfg(feature = "stats")]
+        $self.counters.$counter.fetch_add(1, Ordering::Relaxed);
+    };
 }
 
 impl TransTable {
-    pub fn new() -> Self {
-        let inner = Box::<[MaybeUninit<HashTableEntry>; T_TABLE_SIZE]>::new_uninit();
-        let mut inner = unsafe { inner.assume_init() };
+    pub fn with_capacity_mb(mb: usize) -> Self {
+        Self::with_clusters(Self::clusters_in_mb(mb))
+    }
+
+    fn clusters_in_mb(mb: usize) -> usize {
+        (mb * 1024 * 1024 / (size_of::<HashTableEntry>() * T_TABLE_CLUSTER_SIZE)).max(1)
+    }
+
+    fn with_clusters(clusters: usize) -> Self {
+        Self {
+            inner: (0..clusters * T_TABLE_CLUSTER_SIZE).map(|_| HashTableEntry::default()).collect(),
 
-        for i in inner.iter_mut() {
-            *i = MaybeUninit::new(HashTableEntry {
-                hash: AtomicU64::new(0),
-                age_depth: AtomicU64::new(0),
-                eval_checksum: AtomicU64::new(0),
-            });
+            #[cfg(feature = "stats")]
+            counters: Counters::default(),
         }
+    }
 
-        let inner = unsafe { transmute(inner) };
+    /// drops everything in the table, should only be called between searches
+    #[cfg(feature = "uci")]
+    pub fn resize(&mut self, mb: usize) {
+        let clusters = Self::clusters_in_mb(mb);
 
-        Self {
-            inner,
+        if clusters * T_TABLE_CLUSTER_SIZE != self.inner.len() {
+            // free the old table first so we don't need both at once
+            self.inner = Box::new([]);
+            *self = Self::with_clusters(clusters);
+        }
+    }
+
+    pub fn clear(&self) {
+        self.inner.par_iter().for_each(|e| e.store(0, 0));
+    }
+
+    /// per mille of the sampled entries that were written in the current search
+    pub fn hashfull(&self, age: usize) -> usize {
+        let sample = &self.inner[..self.inner.len().min(1000)];
+        let used = sample.iter()
+            .filter(|e| {
+                let data = e.data.load(Ordering::Relaxed);
+                data != 0 && TransTableEntry::unpack(data).age == age & AGE_MASK
+            })
+            .count();
+
+        used * 1000 / sample.len()
+    }
+
+    /// only counted with the `stats` feature
+    #[cfg_attr(not(feature = "stats"), allow(clippy::missing_const_for_fn))]
+    pub fn stats(&self) -> Option<TransTableStats> {
+        #[cfg(feature = "stats")]
+        return Some(TransTableStats {
+            probes: self.counters.probes.load(Ordering::Relaxed),
+            hits: self.counters.hits.load(Ordering::Relaxed),
+            stores: self.counters.stores.load(Ordering::Relaxed),
+            collisions: self.counters.collisions.load(Ordering::Relaxed),
+        });
+
+        #[cfg(not(feature = "stats"))]
+        None
+    }
+
+    #[cfg_attr(not(feature = "stats"), allow(clippy::missing_const_for_fn))]
+    pub fn reset_stats(&self) {
+        #[cfg(feature = "stats")]
+        for c in [
+            &self.counters.probes,
+            &self.counters.hits,
+            &self.counters.stores,
+            &self.counters.collisions,
+        ] {
+            c.store(0, Ordering::Relaxed);
         }
     }
 
-    pub fn insert(&self, k: u64, v: TransTableEntry) {
-        let checksum = murmur3::murmur3_32(&mut v.to_u8s(), 0).unwrap();
+    /// dumps the table with a header describing the entry layout and zobrist keys it was made with
+    #[cfg(feature = "uci")]
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut file = io::BufWriter::new(std::fs::File::create(path)?);
 
-        let age_depth = ((v.age as u64) << 32) | ((v.depth as u64) & 0xffff_ffff);
-        let eval_checksum = ((v.eval as u64) << 32) | (checksum as u64);
+        file.write_all(FILE_MAGIC)?;
+        file.write_all(&FILE_VERSION.to_le_bytes())?;
+        file.write_all(&ENTRY_LAYOUT.to_le_bytes())?;
+        file.write_all(&(size_of::<HashTableEntry>() as u32).to_le_bytes())?;
+        file.write_all(&(T_TABLE_CLUSTER_SIZE as u32).to_le_bytes())?;
+        file.write_all(&zobrist_fingerprint().to_le_bytes())?;
+        file.write_all(&(self.inner.len() as u64).to_le_bytes())?;
+
+        for e in self.inner.iter() {
+            file.write_all(&e.hash.load(Ordering::Relaxed).to_le_bytes())?;
+            file.write_all(&e.data.load(Ordering::Relaxed).to_le_bytes())?;
+        }
+
+        file.flush()
+    }
+
+    /// inserts every entry of a file made by `save` as if it was written in search `age`, the
+    /// table sizes don't have to match
+    ///
+    /// files from an incompatible build, truncated or corrupt ones are refused without touching the
+    /// table
+    #[cfg(feature = "uci")]
+    pub fn load(&self, path: impl AsRef<Path>, age: usize) -> io::Result<()> {
+        let mut file = io::BufReader::new(std::fs::File::open(path)?);
+
+        let mut magic = [0; 8];
+        file.read_exact(&mut magic)?;
+        if &magic != FILE_MAGIC {
+            return Err(invalid_data("not a transposition table file"));
+        }
+
+        if read_u32(&mut file)? != FILE_VERSION {
+            return Err(invalid_data("unsupported file version"));
+        }
+
+        if read_u32(&mut file)? != ENTRY_LAYOUT
+            || read_u32(&mut file)? != size_of::<HashTableEntry>() as u32
+            || read_u32(&mut file)? != T_TABLE_CLUSTER_SIZE as u32 {
+            return Err(invalid_data("incompatible entry layout"));
+        }
+
+        if read_u64(&mut file)? != zobrist_fingerprint() {
+            return Err(invalid_data("incompatible zobrist hashing"));
+        }
+
+        // the whole body is read and checked first so a broken file leaves the table alone
+        let len = read_u64(&mut file)?;
+        let mut body = Vec::new();
+        file.read_to_end(&mut body)?;
+
+        if body.len() as u64 != len.saturating_mul(2 * size_of::<u64>() as u64) {
+            return Err(invalid_data("truncated or corrupt file"));
+        }
+
+        for e in body.chunks_exact(2 * size_of::<u64>()) {
+            let hash = u64::from_le_bytes(e[..8].try_into().unwrap());
+            let data = u64::from_le_bytes(e[8..].try_into().unwrap());
+
+            if data != 0 {
+                self.insert(hash ^ data, TransTableEntry {
+                    age,
+                    ..TransTableEntry::unpack(data)
+                });
+            }
+        }
 
-        let idx = k as usize & MASK;
-        self.inner[idx].hash.store(k ^ age_depth, Ordering::Relaxed);
-        self.inner[idx].age_depth.store(age_depth, Ordering::Relaxed);
-        self.inner[idx].eval_checksum.store(eval_checksum, Ordering::Relaxed);
+        Ok(())
+    }
+
+    #[cfg(feature = "uci")]
+    pub fn size_mb(&self) -> usize {
+        (self.inner.len() * size_of::<HashTableEntry>()).div_ceil(1024 * 1024)
+    }
+
+    #[inline(always)]
+    fn cluster(&self, k: u64) -> &[HashTableEntry] {
+        // maps the hash onto [0, clusters) without needing a power of 2 size
+        let clusters = self.inner.len() / T_TABLE_CLUSTER_SIZE;
+        let idx = ((k as u128 * clusters as u128) >> 64) as usize * T_TABLE_CLUSTER_SIZE;
+        &self.inner[idx..idx + T_TABLE_CLUSTER_SIZE]
+    }
+
+    /// replaces the entry of the same position if there is one, otherwise the entry from the
+    /// oldest search or with the lowest depth
+    pub fn insert(&self, k: u64, mut v: TransTableEntry) {
+        count!(self.stores);
+
+        let cluster = self.cluster(k);
+
+        let mut victim = 0;
+        let mut victim_worth = isize::MAX;
+        let mut same_position = false;
+
+        for (i, e) in cluster.iter().enumerate() {
+            let (key, data) = e.load();
+
+            if key == k {
+                victim = i;
+                same_position = true;
+
+                // keep the old move around if we didn't find a new one
+                if v.next.is_none() {
+                    v.next = unpack_move((data >> 32) as u16);
+                }
+
+                break;
+            }
+
+            let old = TransTableEntry::unpack(data);
+            let age_diff = v.age.wrapping_sub(old.age) & AGE_MASK;
+            let worth = old.depth as isize - age_diff as isize * AGE_WEIGHT;
+
+            if worth < victim_worth {
+                victim = i;
+                victim_worth = worth;
+            }
+        }
+
+        if !same_position && cluster[victim].data.load(Ordering::Relaxed) != 0 {
+            count!(self.collisions);
+        }
+
+        cluster[victim].store(k, v.pack());
     }
 
     pub fn get(&self, k: u64) -> Option<TransTableEntry> {
-        let idx = k as usize & MASK;
-        let hash = self.inner[idx].hash.load(Ordering::Relaxed);
-        let age_depth = self.inner[idx].age_depth.load(Ordering::Relaxed);
+        count!(self.probes);
 
-        if (hash ^ age_depth) != k {
-            return None;
+        for e in self.cluster(k) {
+            let (key, data) = e.load();
+
+            if key == k && data != 0 {
+                count!(self.hits);
+                return Some(TransTableEntry::unpack(data));
+            }
         }
 
-        let eval_checksum = self.inner[idx].eval_checksum.load(Ordering::Relaxed);
-        let checksum = eval_checksum as u32;
-        let entry = TransTableEntry {
-            depth: age_depth as usize & 0xffff_ffff,
-            age: (age_depth >> 32) as usize,
-            eval: (eval_checksum >> 32) as i32,
-        };
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SLOTS: usize = 1024;
+
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+    }
 
-        if checksum == murmur3::murmur3_32(&mut entry.to_u8s(), 0).unwrap() {
-            Some(entry)
-        } else {
-            None
+    fn entry(depth: usize, age: usize) -> TransTableEntry {
+        TransTableEntry {
+            depth,
+            eval: depth as i32,
+            age,
+            next: None,
+            bound: Bound::Exact,
         }
     }
+
+    /// hit rate of the deep entries after each search got flooded by shallow ones
+    fn bench(mut insert: impl FnMut(u64, TransTableEntry), get: impl Fn(u64) -> bool) -> f32 {
+        let mut rng = XorShift(0x9e37_79b9_7f4a_7c15);
+        let mut key = || rng.next();
+
+        let mut hits = 0;
+        let mut probes = 0;
+
+        for age in 1..=32 {
+            let deep = (0..SLOTS / 8).map(|_| key()).collect::<Vec<_>>();
+
+            for (i, k) in deep.iter().enumerate() {
+                insert(*k, entry(6 + i % 4, age));
+
+                for _ in 0..8 {
+                    insert(key(), entry(1, age));
+                }
+            }
+
+            probes += deep.len();
+            hits += deep.iter().filter(|k| get(**k)).count();
+        }
+
+        hits as f32 / probes as f32
+    }
+
+    #[test]
+    fn cluster_replacement_keeps_deep_entries() {
+        let table = TransTable::with_clusters(SLOTS / T_TABLE_CLUSTER_SIZE);
+        let bucketed = bench(|k, v| table.insert(k, v), |k| table.get(k).is_some());
+
+        // what `insert` used to do: one entry per index that always gets overwritten
+        let direct = std::cell::RefCell::new(vec![0; SLOTS]);
+        let direct_mapped = bench(
+            |k, _| direct.borrow_mut()[k as usize % SLOTS] = k,
+            |k| direct.borrow()[k as usize % SLOTS] == k,
+        );
+
+        assert!(bucketed > 0.9);
+        assert!(bucketed > direct_mapped * 1.5);
+    }
+
+    #[test]
+    fn same_position_is_replaced_in_place() {
+        let table = TransTable::with_clusters(1);
+        let m = ChessMove::new(Square::E2, Square::E4, None);
+
+        table.insert(42, TransTableEntry { next: Some(m), ..entry(8, 1) });
+        table.insert(42, entry(3, 2));
+
+        let e = table.get(42).unwrap();
+        assert_eq!(e.depth, 3);
+        assert_eq!(e.next, Some(m));
+        assert_eq!(table.cluster(42).iter().filter(|e| e.data.load(Ordering::Relaxed) != 0).count(), 1);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let path = std::env::temp_dir().join(format!("chessbot_tt_{}", std::process::id()));
+        let from = TransTable::with_capacity_mb(1);
+        let to = TransTable::with_capacity_mb(2);
+
+        let mut rng = XorShift(7);
+        let keys = (0..1000).map(|_| rng.next()).collect::<Vec<_>>();
+        keys.iter().for_each(|k| from.insert(*k, entry(5, 3)));
+
+        from.save(&path).unwrap();
+        to.load(&path, 9).unwrap();
+
+        assert!(keys.iter().all(|k| to.get(*k).is_some_and(|e| e.depth == 5 && e.age == 9)));
+
+        // a file from a build with another entry layout
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[12] ^= 1;
+        std::fs::write(&path, bytes).unwrap();
+
+        let other = TransTable::with_capacity_mb(1);
+        assert_eq!(other.load(&path, 1).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert!(keys.iter().all(|k| other.get(*k).is_none()));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn truncated_file_leaves_the_table_alone() {
+        let path = std::env::temp_dir().join(format!("chessbot_tt_truncated_{}", std::process::id()));
+        let from = TransTable::with_capacity_mb(1);
+        let to = TransTable::with_capacity_mb(1);
+
+        let mut rng = XorShift(5);
+        let saved = (0..1000).map(|_| rng.next()).collect::<Vec<_>>();
+        let kept = (0..1000).map(|_| rng.next()).collect::<Vec<_>>();
+        saved.iter().for_each(|k| from.insert(*k, entry(5, 3)));
+        kept.iter().for_each(|k| to.insert(*k, entry(7, 1)));
+
+        from.save(&path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[..bytes.len() / 2]).unwrap();
+
+        let before = to.inner.iter().map(|e| e.load()).collect::<Vec<_>>();
+        assert_eq!(to.load(&path, 9).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(to.inner.iter().map(|e| e.load()).collect::<Vec<_>>(), before);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn concurrent_access_never_returns_torn_entries() {
+        // few slots and many threads so writes to the same entry keep racing each other
+        let table = TransTable::with_clusters(2);
+        let keys = {
+            let mut rng = XorShift(3);
+            (0..64).map(|_| rng.next()).collect::<Vec<_>>()
+        };
+
+        let expected = |k: u64| TransTableEntry {
+            eval: (k >> 32) as i32,
+            next: Some(ChessMove::new(
+                Square::make_square(Rank::from_index(k as usize % 8), File::from_index((k >> 3) as usize % 8)),
+                Square::make_square(Rank::from_index((k >> 6) as usize % 8), File::from_index((k >> 9) as usize % 8)),
+                None,
+            )),
+            ..entry(1 + (k >> 12) as usize % 200, (k >> 20) as usize)
+        };
+
+        std::thread::scope(|s| {
+            for t in 0..8 {
+                let table = &table;
+                let keys = &keys;
+
+                s.spawn(move || {
+                    let mut rng = XorShift(t + 11);
+
+                    for _ in 0..100_000 {
+                        let k = keys[rng.next() as usize % keys.len()];
+
+                        if rng.next() & 1 == 0 {
+                            table.insert(k, expected(k));
+                        } else if let Some(e) = table.get(k) {
+                            let want = expected(k);
+                            assert_eq!(
+                                (e.eval, e.next, e.depth, e.age & AGE_MASK, e.bound),
+                                (want.eval, want.next, want.depth, want.age & AGE_MASK, want.bound),
+                            );
+                        }
+                    }
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn resize_to_non_power_of_two() {
+        let mut table = TransTable::with_capacity_mb(1);
+        table.insert(u64::MAX, entry(5, 1));
+        table.resize(3);
+
+        assert_eq!(table.size_mb(), 3);
+        assert!(table.get(u64::MAX).is_none());
+
+        let mut rng = XorShift(1);
+        let keys = (0..1000).map(|_| rng.next()).collect::<Vec<_>>();
+        keys.iter().for_each(|k| table.insert(*k, entry(5, 1)));
+
+        assert!(keys.iter().all(|k| table.get(*k).is_some()));
+
+        table.clear();
+        assert!(keys.iter().all(|k| table.get(*k).is_none()));
+    }
 }
@@ -0,0 +1,219 @@
+use chess::*;
+use rayon::prelude::*;
+use std::str::FromStr;
+use super::cache::Cache;
+use super::eval::{self, Accumulator};
+use super::params::EvalParams;
+use super::{config, Game};
+
+/// a quiet position and how the game it came from ended, 1 being a white win
+struct Entry {
+    board: Board,
+    result: f64,
+}
+
+/// texel tuning: `tune <data> <out.json> [passes]`
+///
+/// every line of the data file is a fen followed by the result, either as `[1.0]`, `[1-0]` or
+/// epd style as `c9 "1-0";`
+pub fn run(args: &[String]) {
+    let (Some(data), Some(out)) = (args.first(), args.get(1)) else {
+        warn!("usage: tune <data> <out.json> [passes]");
+        return;
+    };
+    let passes = args.get(2).and_then(|p| p.parse().ok()).unwrap_or(usize::MAX);
+
+    let data = match std::fs::read_to_string(data) {
+        Ok(data) => data,
+        Err(e) => {
+            warn!("failed to read `{}` ({})", data, e);
+            return;
+        },
+    };
+
+    let game = Game::with_hash_size(Board::default(), Vec::new(), 1);
+
+    let entries = game.pool.install(|| data.par_lines().filter_map(|line| {
+        let (board, result) = parse_line(line)?;
+        Some(Entry { board: game.quiet_position(board), result })
+    }).collect::<Vec<_>>());
+
+    if entries.is_empty() {
+        warn!("no positions to tune on");
+        return;
+    }
+
+    info!("loaded {} positions", entries.len());
+
+    let pawn_table = Cache::with_capacity_mb(config::PAWN_TABLE_MB);
+    // steps past what a weight can hold are never an improvement
+    let error = |values: &[i32], k: f64| match EvalParams::from_slice(values) {
+        Ok(params) => game.pool.install(|| error(&entries, &params, k, &pawn_table)),
+        Err(_) => f64::INFINITY,
+    };
+
+    let mut values = game.params.to_vec();
+    let k = fit_k(|k| error(&values, k));
+    let mut best = error(&values, k);
+
+    info!("scaling constant {:.4}, starting error {:.6}", k, best);
+
+    // local search until nothing improves anymore
+    for pass in 0..passes {
+        let improved = local_search_pass(&mut values, &mut best, |values| error(values, k));
+
+        info!("pass {} done, error {:.6}", pass + 1, best);
+
+        // only steps that fit are kept
+        if let Err(e) = EvalParams::from_slice(&values).unwrap().save(out) {
+            warn!("failed to save eval parameters to `{}` ({})", out, e);
+            return;
+        }
+
+        if !improved {
+            break;
+        }
+    }
+
+    info!("saved tuned eval parameters to `{}`", out);
+}
+
+/// nudges every weight by one, keeping the change if it lowers the error
+fn local_search_pass(values: &mut [i32], best: &mut f64, error: impl Fn(&[i32]) -> f64) -> bool {
+    let mut improved = false;
+
+    for i in 0..values.len() {
+        for delta in [1, -1] {
+            values[i] += delta;
+            let e = error(values);
+
+            if e < *best {
+                *best = e;
+                improved = true;
+                break;
+            }
+
+            values[i] -= delta;
+        }
+    }
+
+    improved
+}
+
+fn parse_line(line: &str) -> Option<(Board, f64)> {
+    let (fen, label) = line.split_at(line.find(['[', '"'])?);
+
+    let label = label.trim_matches(|c: char| matches!(c, '[' | ']' | '"' | ';') || c.is_whitespace());
+    let result = match label {
+        "1-0" => 1.0,
+        "0-1" => 0.0,
+        "1/2-1/2" => 0.5,
+        label => label.parse().ok()?,
+    };
+
+    // anything past the first four fields is either move counters or epd opcodes
+    let fen = fen.split_whitespace().take(4).collect::<Vec<_>>().join(" ");
+
+    Some((Board::from_str(&fen).ok()?, result))
+}
+
+/// mean squared error between the results and the eval mapped to a win probability
+fn error(entries: &[Entry], params: &EvalParams, k: f64, pawn_table: &Cache) -> f64 {
+    // pawn structure cached with other weights would be stale
+    pawn_table.clear();
+
+    entries.par_iter().map(|entry| {
+        let eval = eval::evaluate(&entry.board, &Accumulator::new(&entry.board, params, None), pawn_table);
+        let eval = if entry.board.side_to_move() == Color::White { eval } else { -eval };
+
+        (entry.result - sigmoid(eval, k)).powi(2)
+    }).sum::<f64>() / entries.len() as f64
+}
+
+fn sigmoid(eval: i32, k: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * eval as f64 / 400.0))
+}
+
+/// scales evals to win probabilities as well as the untuned weights allow
+fn fit_k(error: impl Fn(f64) -> f64) -> f64 {
+    let mut k = 1.0;
+    let mut best = error(k);
+    let mut step = 0.5;
+
+    loop {
+        let mut improved = false;
+
+        for candidate in [k - step, k + step] {
+            let e = error(candidate);
+
+            if e < best {
+                best = e;
+                k = candidate;
+                improved = true;
+            }
+        }
+
+        if !improved {
+            step /= 2.0;
+
+            if step < 0.001 {
+                break k;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_every_result_notation() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+        for (line, result) in [
+            (format!("{start} [1.0]"), 1.0),
+            (format!("{start} [0.5]"), 0.5),
+            (format!("{start} [1-0]"), 1.0),
+            (format!("{start} [1/2-1/2]"), 0.5),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - c9 \"0-1\";".to_string(), 0.0),
+        ] {
+            assert_eq!(parse_line(&line), Some((Board::default(), result)), "{line}");
+        }
+
+        assert_eq!(parse_line(start), None);
+        assert_eq!(parse_line(&format!("{start} [win]")), None);
+        assert_eq!(parse_line("not a fen [1-0]"), None);
+    }
+
+    #[test]
+    fn fits_k_and_improves_the_error() {
+        let entries = [
+            ("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1", 1.0),
+            ("4k3/8/8/8/8/8/3PP3/4K3 w - - 0 1", 1.0),
+            ("4k3/4p3/8/8/8/8/8/4K3 w - - 0 1", 0.0),
+            ("4k3/3pp3/8/8/8/8/8/4K3 b - - 0 1", 0.0),
+            ("4k3/4p3/8/8/8/8/4P3/4K3 w - - 0 1", 0.5),
+            ("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 1", 0.5),
+            ("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNB1KBNR w KQkq - 0 1", 0.0),
+        ].map(|(fen, result)| Entry { board: Board::from_str(fen).unwrap(), result });
+
+        let pawn_table = Cache::with_capacity_mb(1);
+        let params = EvalParams::default();
+        let error = |params: &EvalParams, k| error(&entries, params, k, &pawn_table);
+
+        // the fitted k is at least as good as its neighbours
+        let k = fit_k(|k| error(&params, k));
+        assert!(error(&params, k) <= error(&params, k * 0.9));
+        assert!(error(&params, k) <= error(&params, k * 1.1));
+
+        // a single pass over every weight finds something better on a set this small
+        let mut values = params.to_vec();
+        let start = error(&params, k);
+        let mut best = start;
+
+        assert!(local_search_pass(&mut values, &mut best, |values| error(&EvalParams::from_slice(values).unwrap(), k)));
+        assert!(best < start);
+        assert_eq!(error(&EvalParams::from_slice(&values).unwrap(), k), best);
+    }
+}
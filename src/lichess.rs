@@ -7,6 +7,19 @@ use chess::*;
 const DISALLOWED_TIME_CONTROLS: &[&str] = &["correspondence", "classical"];
 const EXCEPTION_USERS: &[&str] = &["funnsam"];
 const ACCEPT_RATED: bool = false;
+// memory for all games together, split into a fixed slot per game. a slot holds the
+// transposition table along with the eval cache and pawn table, the search threads are shared
+const HASH_BUDGET_MB: usize = 256;
+const MAX_GAMES: usize = 4;
+const SLOT_HASH_MB: usize = HASH_BUDGET_MB / MAX_GAMES - config::EVAL_CACHE_MB - config::PAWN_TABLE_MB;
+// picked up at startup if it exists, otherwise the classical eval is used
+const EVAL_FILE: &str = "nnue.bin";
+// same for the opening book, and how it's used
+const BOOK_FILE: &str = "book.bin";
+const BOOK_SELECTION: book::Selection = book::Selection::Weighted;
+const BOOK_DEPTH: usize = config::DEFAULT_BOOK_DEPTH;
+// rating points per centipawn of contempt, so 300 points stronger avoids draws worth up to 30 cp
+const CONTEMPT_RATING_STEP: i32 = 10;
 
 pub struct LichessClient {
     client: Client,
@@ -15,6 +28,8 @@ pub struct LichessClient {
     api_token: String,
 
     pub active_games: AtomicUsize,
+    network: Option<Arc<nnue::Network>>,
+    book: Option<Arc<book::Book>>,
 }
 
 impl LichessClient {
@@ -33,12 +48,36 @@ impl LichessClient {
             .connection_verbose(true)
             .build().unwrap();
 
+        let network = std::path::Path::new(EVAL_FILE).exists().then(|| match nnue::Network::load(EVAL_FILE) {
+            Ok(network) => {
+                info!("loaded network from `{}`", EVAL_FILE);
+                Some(Arc::new(network))
+            },
+            Err(e) => {
+                warn!("failed to load network from `{}` ({})", EVAL_FILE, e);
+                None
+            },
+        }).flatten();
+
+        let book = std::path::Path::new(BOOK_FILE).exists().then(|| match book::Book::load(BOOK_FILE) {
+            Ok(book) => {
+                info!("loaded opening book from `{}` ({} entries)", BOOK_FILE, book.entries().len());
+                Some(Arc::new(book))
+            },
+            Err(e) => {
+                warn!("failed to load opening book from `{}` ({})", BOOK_FILE, e);
+                None
+            },
+        }).flatten();
+
         Self {
             client,
 
             api_token,
 
             active_games: AtomicUsize::new(0),
+            network,
+            book,
         }
     }
 
@@ -75,11 +114,13 @@ impl LichessClient {
                     let variant = challenge["variant"]["key"].as_str().unwrap();
                     let time_ctrl = challenge["speed"].as_str().unwrap();
                     let is_rated = challenge["rated"].as_bool().unwrap();
-                    if EXCEPTION_USERS.contains(&user) || (
+                    // the slot is only taken once the game starts
+                    let has_slot = self.active_games.load(Ordering::Relaxed) < MAX_GAMES;
+                    if has_slot && (EXCEPTION_USERS.contains(&user) || (
                         variant == "standard"
                         && !DISALLOWED_TIME_CONTROLS.contains(&time_ctrl)
                         && (ACCEPT_RATED || !is_rated)
-                    ) {
+                    )) {
                         info!("`{}` challenged bot (id: `{}`)", user, id);
 
                         // FIX: post req
@@ -117,13 +158,24 @@ impl LichessClient {
 
                     info!("started a game with `{}` (id: `{}`, fen: `{}`)", user, id, fen);
 
-                    let game = crate::bot::Game::new(board, Vec::new());
+                    let Some(slot) = GameSlot::take(&self) else {
+                        warn!("no memory left for another game, aborting `{}`", id);
+                        self.abort_game(&id).await;
+                        continue;
+                    };
+
+                    let contempt = self.contempt(game).await;
+
+                    let mut game = crate::bot::Game::with_hash_size(board, Vec::new(), SLOT_HASH_MB);
+                    game.network = self.network.clone();
+                    game.contempt = contempt;
+                    game.book = self.book.clone();
+                    game.book_selection = BOOK_SELECTION;
+                    game.book_depth = BOOK_DEPTH;
                     let arc = Arc::clone(&self);
-                    tokio::spawn(async move { arc.play_game(id, game, color).await });
-                },
-                Some("gameFinish") => {
-                    self.active_games.fetch_add(1, Ordering::Relaxed);
+                    tokio::spawn(async move { arc.play_game(id, game, color, slot).await });
                 },
+                Some("gameFinish") => {},
                 Some("challengeCanceled" | "challengeDeclined") => {},
                 Some(typ) => {
                     warn!("got unknown type of event `{}`", typ);
@@ -137,7 +189,7 @@ impl LichessClient {
         }
     }
 
-    async fn play_game(self: Arc<Self>, game_id: String, mut game: crate::bot::Game, color: Color) {
+    async fn play_game(self: Arc<Self>, game_id: String, mut game: crate::bot::Game, color: Color, slot: GameSlot) {
         let color_prefix = if matches!(color, Color::White) {
             "w"
         } else {
@@ -174,7 +226,7 @@ impl LichessClient {
                         };
 
                         ignore_next = true;
-                        let next = game.play();
+                        let next = game.play().best_move;
                         self.send_move(&game_id, next).await;
                     }
                 },
@@ -194,7 +246,7 @@ impl LichessClient {
                         };
 
                         ignore_next = true;
-                        let next = game.play();
+                        let next = game.play().best_move;
                         self.send_move(&game_id, next).await;
                     } else {
                         ignore_next = false;
@@ -211,9 +263,50 @@ impl LichessClient {
             }
         }
 
+        // free the tables before another game can take the slot
+        drop(game);
+        drop(slot);
+
         info!("stream ended (id: `{}`)", game_id);
     }
 
+    /// positive against weaker opponents so the bot plays on instead of taking a repetition,
+    /// negative against stronger ones. unrated opponents like the lichess ai get none
+    async fn contempt(&self, game: &json::JsonValue) -> i32 {
+        let Some(theirs) = game["opponent"]["rating"].as_i32() else { return config::DEFAULT_CONTEMPT };
+        let perf = game["perf"].as_str().or_else(|| game["speed"].as_str()).unwrap_or("");
+
+        let Some(ours) = self.rating(perf).await else {
+            warn!("failed to get own `{}` rating", perf);
+            return config::DEFAULT_CONTEMPT;
+        };
+
+        let contempt = ((ours - theirs) / CONTEMPT_RATING_STEP).clamp(-config::MAX_CONTEMPT, config::MAX_CONTEMPT);
+        info!("contempt {} ({} vs {})", contempt, ours, theirs);
+
+        contempt
+    }
+
+    async fn rating(&self, perf: &str) -> Option<i32> {
+        let resp = self.client.execute(
+            self.client
+                .get("https://lichess.org/api/account")
+                .build().unwrap()
+        ).await.ok()?;
+
+        let account = json::parse(&resp.text().await.ok()?).ok()?;
+        account["perfs"][perf]["rating"].as_i32()
+    }
+
+    async fn abort_game(&self, game_id: &str) {
+        if self.client.execute(self.client
+            .post(format!("https://lichess.org/api/bot/game/{game_id}/abort"))
+            .build().unwrap()
+        ).await.ok().and_then(|a| a.status().is_success().then_some(())).is_none() {
+            warn!("failed to abort game id {}", game_id);
+        }
+    }
+
     async fn send_move(&self, game_id: &str, m: ChessMove) {
         // FIX: this fucking post request is hanging the streams
         // https://github.com/seanmonstar/reqwest/issues/2133
@@ -256,6 +349,24 @@ impl LichessClient {
     }
 }
 
+/// one of the `MAX_GAMES` slots, given back when dropped so a panicking game doesn't leak it
+struct GameSlot(Arc<LichessClient>);
+
+impl GameSlot {
+    fn take(client: &Arc<LichessClient>) -> Option<Self> {
+        client.active_games
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| (n < MAX_GAMES).then_some(n + 1))
+            .ok()
+            .map(|_| Self(Arc::clone(client)))
+    }
+}
+
+impl Drop for GameSlot {
+    fn drop(&mut self) {
+        self.0.active_games.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
 struct NdJsonIter<S: Send + futures_util::stream::Stream<Item = Result<bytes::Bytes>>> {
     stream: S,
     buffer: Vec<u8>,
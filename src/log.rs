@@ -1,3 +1,4 @@
+#[allow(unused_macros)]
 macro_rules! dbg {
     ($fmt: tt $($args: tt)*) => {
         eprintln!(concat!("\x1b[90mDebug: ", $fmt, "\x1b[0m") $($args)*);
@@ -16,6 +17,7 @@ macro_rules! warn {
     };
 }
 
+#[allow(unused_macros)]
 macro_rules! error {
     ($fmt: tt $($args: tt)*) => {
         eprintln!(concat!("\x1b[1;31mError:\x1b[0m ", $fmt) $($args)*);
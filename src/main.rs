@@ -11,8 +11,6 @@
     clippy::just_underscores_and_digits,
 )]
 
-#![feature(new_uninit)]
-
 #[macro_use]
 mod log;
 mod bot;
@@ -26,22 +24,21 @@ mod uci;
 #[cfg(feature = "lichess")]
 #[tokio::main]
 async fn main() {
-    rayon::ThreadPoolBuilder::new()
-        .stack_size(16 * 1024 * 1024)
-        .thread_name(|i| format!("rayon_pool_thread_{i}"))
-        .build_global().unwrap();
-
+    // built before any game starts so the first search that reaches one doesn't pay for it
+    bot::kpk::init();
     lichess::LichessClient::new().start().await;
 }
 
 #[cfg(feature = "uci")]
 fn main() {
-    rayon::ThreadPoolBuilder::new()
-        .stack_size(16 * 1024 * 1024)
-        .thread_name(|i| format!("rayon_pool_thread_{i}"))
-        .build_global().unwrap();
-
-    uci::UciClient::new().start();
+    let args = std::env::args().collect::<Vec<_>>();
+    bot::kpk::init();
+
+    match (args.get(1).map(String::as_str), args.get(2).map(String::as_str)) {
+        (Some("tune"), _) => bot::tune::run(&args[2..]),
+        (Some("book"), Some("build")) => bot::book_build::run(&args[3..]),
+        _ => uci::UciClient::new().start(),
+    }
 }
 
 #[cfg(all(feature = "lichess", feature = "uci"))]
@@ -1,5 +1,6 @@
 use std::io::{self, BufRead as _};
 use std::str::FromStr;
+use std::sync::Arc;
 use chess::*;
 use super::bot::*;
 
@@ -7,8 +8,8 @@ pub struct UciClient {
 }
 
 impl UciClient {
-    pub fn new() -> Self {
-        UciClient {}
+    pub const fn new() -> Self {
+        Self {}
     }
 
     pub fn start(self) {
@@ -23,13 +24,122 @@ impl UciClient {
             match parse_command(tokens) {
                 Some(UciCommand::Uci) => {
                     println!("id name funn's bot");
+                    println!("option name Threads type spin default {} min 1 max 256", default_threads());
+                    println!("option name Hash type spin default {} min 1 max 65536", config::DEFAULT_HASH_MB);
+                    println!("option name Contempt type spin default {} min {} max {}", config::DEFAULT_CONTEMPT, -config::MAX_CONTEMPT, config::MAX_CONTEMPT);
+                    println!("option name BookFile type string default <empty>");
+                    println!("option name BookSelection type combo default weighted var best var weighted");
+                    println!("option name BookDepth type spin default {} min 0 max 1024", config::DEFAULT_BOOK_DEPTH);
+                    println!("option name EvalFile type string default <empty>");
+                    println!("option name EvalParams type string default <empty>");
                     println!("uciok");
                 },
                 Some(UciCommand::IsReady) => println!("readyok"),
                 Some(UciCommand::Stop) => std::process::exit(0),
-                Some(UciCommand::UciNewGame) => {},
+                Some(UciCommand::SaveHash(path)) => match game.save_hash(&path) {
+                    Ok(()) => {
+                        info!("saved transposition table to `{}`", path);
+                    },
+                    Err(e) => {
+                        warn!("failed to save transposition table to `{}` ({})", path, e);
+                    },
+                },
+                Some(UciCommand::LoadHash(path)) => match game.load_hash(&path) {
+                    Ok(()) => {
+                        info!("loaded transposition table from `{}`", path);
+                    },
+                    Err(e) => {
+                        warn!("failed to load transposition table from `{}` ({})", path, e);
+                    },
+                },
+                Some(UciCommand::Eval) => println!("{}", game.eval_trace()),
+                Some(UciCommand::SaveParams(path)) => match game.params.save(&path) {
+                    Ok(()) => {
+                        info!("saved eval parameters to `{}`", path);
+                    },
+                    Err(e) => {
+                        warn!("failed to save eval parameters to `{}` ({})", path, e);
+                    },
+                },
+                Some(UciCommand::UciNewGame) => game.clear_hash(),
+                Some(UciCommand::SetOption { name, value }) => {
+                    let number = value.as_deref().and_then(|v| v.parse().ok());
+
+                    match (name.to_ascii_lowercase().as_str(), number) {
+                        ("threads", Some(threads)) => game.set_threads(threads),
+                        ("hash", Some(mb)) => game.set_hash_size(mb),
+                        ("bookdepth", Some(depth)) => game.book_depth = depth,
+                        ("threads" | "hash" | "bookdepth", None) => {
+                            warn!("invalid value for option `{}`", name);
+                        },
+                        // can be negative, so it isn't in `number`
+                        ("contempt", _) => match value.as_deref().and_then(|v| v.trim().parse::<i32>().ok()) {
+                            Some(contempt) => game.contempt = contempt.clamp(-config::MAX_CONTEMPT, config::MAX_CONTEMPT),
+                            None => {
+                                warn!("invalid value for option `{}`", name);
+                            },
+                        },
+                        ("bookselection", _) => match value.as_deref().unwrap_or("").parse() {
+                            Ok(selection) => game.book_selection = selection,
+                            Err(()) => {
+                                warn!("invalid value for option `{}`", name);
+                            },
+                        },
+                        // an empty path turns the book off
+                        ("bookfile", _) => match value.as_deref().unwrap_or("").trim() {
+                            "" | "<empty>" => {
+                                game.book = None;
+                                info!("not using an opening book");
+                            },
+                            path => match book::Book::load(path) {
+                                Ok(book) => {
+                                    info!("loaded opening book from `{}` ({} entries)", path, book.entries().len());
+                                    game.book = Some(Arc::new(book));
+                                },
+                                Err(e) => {
+                                    warn!("failed to load opening book from `{}` ({})", path, e);
+                                },
+                            },
+                        },
+                        // an empty path goes back to the classical eval
+                        ("evalfile", _) => match value.as_deref().unwrap_or("").trim() {
+                            "" | "<empty>" => {
+                                game.set_network(None);
+                                info!("using the classical eval");
+                            },
+                            path => match nnue::Network::load(path) {
+                                Ok(network) => {
+                                    game.set_network(Some(Arc::new(network)));
+                                    info!("loaded network from `{}`", path);
+                                },
+                                Err(e) => {
+                                    warn!("failed to load network from `{}` ({})", path, e);
+                                },
+                            },
+                        },
+                        // an empty path goes back to the built in weights
+                        ("evalparams", _) => match value.as_deref().unwrap_or("").trim() {
+                            "" | "<empty>" => {
+                                game.set_params(params::EvalParams::default());
+                                info!("using the default eval parameters");
+                            },
+                            path => match params::EvalParams::load(path) {
+                                Ok(params) => {
+                                    game.set_params(params);
+                                    info!("loaded eval parameters from `{}`", path);
+                                },
+                                Err(e) => {
+                                    warn!("failed to load eval parameters from `{}` ({})", path, e);
+                                },
+                            },
+                        },
+                        _ => {
+                            warn!("unknown option `{}`", name);
+                        },
+                    }
+                },
                 Some(UciCommand::Position { mut position, moves }) => {
-                    if moves.len() == 0 || game_hash != position.get_hash() {
+                    if moves.is_empty() || game_hash != position.get_hash() {
                         game_hash = position.get_hash();
 
                         for m in moves.iter() {
@@ -37,18 +147,49 @@ impl UciClient {
                         }
 
                         game.board = position;
-                        game.moves = moves;
                     } else {
                         game.board = game.board.make_move_new(*moves.last().unwrap());
                     }
+
+                    game.moves = moves;
                 },
-                Some(UciCommand::Go { wtime, btime }) => {
+                Some(UciCommand::Go { wtime, btime, limits }) => {
                     game.time_ctrl = if matches!(game.board.side_to_move(), Color::White) {
                         wtime
                     } else {
                         btime
                     };
-                    println!("bestmove {}", game.play());
+                    game.limits = limits;
+
+                    let result = game.play();
+                    println!(
+                        "info depth {} seldepth {} nodes {} time {} hashfull {} score {}{} pv {}",
+                        result.depth,
+                        result.seldepth,
+                        result.nodes,
+                        result.elapsed.as_millis(),
+                        result.hashfull,
+                        result.score,
+                        match result.bound {
+                            Bound::Exact => "",
+                            Bound::Lower => " lowerbound",
+                            Bound::Upper => " upperbound",
+                        },
+                        result.pv_string(),
+                    );
+
+                    if let Some(stats) = result.tt_stats {
+                        println!("info string tt {}", stats);
+                    }
+
+                    if let (Some(eval_cache), Some(pawn_table)) = (result.eval_cache_stats, result.pawn_table_stats) {
+                        println!("info string eval cache {} pawn table {}", eval_cache, pawn_table);
+                    }
+
+                    match result.ponder_move {
+                        Some(ponder) => println!("bestmove {} ponder {}", result.best_move, ponder),
+                        None => println!("bestmove {}", result.best_move),
+                    }
                 },
                 None => {
                     warn!("got unknown uci command");
@@ -64,6 +205,10 @@ enum UciCommand {
     // Debug(bool),
     IsReady,
     UciNewGame,
+    SetOption {
+        name: String,
+        value: Option<String>,
+    },
     Position {
         position: Board,
         moves: Vec<ChessMove>,
@@ -71,8 +216,14 @@ enum UciCommand {
     Go {
         wtime: TimeControl,
         btime: TimeControl,
+        limits: SearchLimits,
     },
     Stop,
+    // extensions
+    Eval,
+    SaveHash(String),
+    LoadHash(String),
+    SaveParams(String),
 }
 
 fn move_from_uci(m: &str) -> ChessMove {
@@ -102,13 +253,34 @@ fn parse_command<'a, T: Iterator<Item = &'a str>>(mut token: T) -> Option<UciCom
         Some("uci") => Some(UciCommand::Uci),
         Some("isready") => Some(UciCommand::IsReady),
         Some("ucinewgame") => Some(UciCommand::UciNewGame),
+        Some("setoption") => {
+            if token.next() != Some("name") {
+                return None;
+            }
+
+            let mut name = Vec::new();
+            let mut value = None::<Vec<&str>>;
+
+            for t in token.by_ref() {
+                match value.as_mut() {
+                    Some(value) => value.push(t),
+                    None if t == "value" => value = Some(Vec::new()),
+                    None => name.push(t),
+                }
+            }
+
+            Some(UciCommand::SetOption {
+                name: name.join(" "),
+                value: value.map(|v| v.join(" ")),
+            })
+        },
         Some("position") => {
             let mut moves = Vec::new();
             let next = token.next();
             let board = if matches!(next, Some("fen")) {
                 let mut fen = String::new();
 
-                while let Some(t) = token.next() {
+                for t in token.by_ref() {
                     if t == "moves" {
                         break;
                     }
@@ -125,7 +297,7 @@ fn parse_command<'a, T: Iterator<Item = &'a str>>(mut token: T) -> Option<UciCom
                 return None;
             };
 
-            while let Some(m) = token.next() {
+            for m in token.by_ref() {
                 moves.push(move_from_uci(m));
             }
 
@@ -139,6 +311,8 @@ fn parse_command<'a, T: Iterator<Item = &'a str>>(mut token: T) -> Option<UciCom
             let mut btime = u32::MAX as usize;
             let mut winc = 0;
             let mut binc = 0;
+            let mut limits = SearchLimits::default();
+            let mut timed = false;
 
             while let Some(t) = token.next() {
                 match t {
@@ -146,8 +320,18 @@ fn parse_command<'a, T: Iterator<Item = &'a str>>(mut token: T) -> Option<UciCom
                     "btime" => btime = token.next()?.parse().ok()?,
                     "winc" => winc = token.next()?.parse().ok()?,
                     "binc" => binc = token.next()?.parse().ok()?,
+                    "depth" => limits.depth = token.next()?.parse().ok()?,
+                    "nodes" => limits.nodes = token.next()?.parse().ok()?,
                     _ => {},
                 }
+
+                timed |= matches!(t, "wtime" | "btime");
+            }
+
+            // fixed depth or node searches without a clock shouldn't be cut short by time
+            if !timed && (limits.depth != SearchLimits::default().depth || limits.nodes != usize::MAX) {
+                wtime = usize::MAX;
+                btime = usize::MAX;
             }
 
             Some(UciCommand::Go {
@@ -159,9 +343,14 @@ fn parse_command<'a, T: Iterator<Item = &'a str>>(mut token: T) -> Option<UciCom
                     time_left: btime,
                     time_incr: binc,
                 },
+                limits,
             })
         },
         Some("stop") => Some(UciCommand::Stop),
+        Some("eval") => Some(UciCommand::Eval),
+        Some("savehash") => Some(UciCommand::SaveHash(token.collect::<Vec<_>>().join(" "))),
+        Some("loadhash") => Some(UciCommand::LoadHash(token.collect::<Vec<_>>().join(" "))),
+        Some("saveparams") => Some(UciCommand::SaveParams(token.collect::<Vec<_>>().join(" "))),
         Some(_) => parse_command(token),
         None => None,
     }